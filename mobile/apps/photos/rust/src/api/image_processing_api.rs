@@ -1,5 +1,8 @@
 use crate::{
-    image::decode::decode_image_from_path,
+    image::{
+        decode::decode_image_from_path,
+        image_compression::{EncodedImageFormat, FACE_THUMBNAIL_JPEG_QUALITY},
+    },
     ml::face::thumbnail::{FaceBox, generate_face_thumbnails as generate_face_thumbnails_impl},
 };
 
@@ -11,9 +14,27 @@ pub struct RustFaceBox {
     pub height: f64,
 }
 
+/// Output format for face thumbnails, selectable per call so a batch can mix a
+/// widely-supported JPEG fallback with smaller WebP/AVIF for capable clients.
+#[derive(Clone, Copy, Debug)]
+pub enum RustThumbnailFormat {
+    Jpeg { quality: u8 },
+    WebP { quality: u8 },
+    Avif { quality: u8, speed: u8 },
+}
+
+impl Default for RustThumbnailFormat {
+    fn default() -> Self {
+        RustThumbnailFormat::Jpeg {
+            quality: FACE_THUMBNAIL_JPEG_QUALITY,
+        }
+    }
+}
+
 pub fn generate_face_thumbnails(
     image_path: String,
     face_boxes: Vec<RustFaceBox>,
+    format: RustThumbnailFormat,
 ) -> Result<Vec<Vec<u8>>, String> {
     let decoded = decode_image_from_path(&image_path).map_err(|e| e.to_string())?;
     let face_boxes = face_boxes
@@ -25,7 +46,27 @@ pub fn generate_face_thumbnails(
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    generate_face_thumbnails_impl(&decoded, &face_boxes).map_err(|e| e.to_string())
+    generate_face_thumbnails_impl(&decoded, &face_boxes, format.try_into()?)
+        .map_err(|e| e.to_string())
+}
+
+impl TryFrom<RustThumbnailFormat> for EncodedImageFormat {
+    type Error = String;
+
+    fn try_from(value: RustThumbnailFormat) -> Result<Self, Self::Error> {
+        match value {
+            RustThumbnailFormat::Jpeg { quality } => Ok(EncodedImageFormat::Jpeg { quality }),
+            RustThumbnailFormat::WebP { quality } => Ok(EncodedImageFormat::WebP { quality }),
+            #[cfg(feature = "avif")]
+            RustThumbnailFormat::Avif { quality, speed } => {
+                Ok(EncodedImageFormat::Avif { quality, speed })
+            }
+            #[cfg(not(feature = "avif"))]
+            RustThumbnailFormat::Avif { .. } => {
+                Err("AVIF face thumbnails require the `avif` feature".to_string())
+            }
+        }
+    }
 }
 
 impl TryFrom<RustFaceBox> for FaceBox {
@@ -51,3 +92,49 @@ impl TryFrom<RustFaceBox> for FaceBox {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jpeg_and_webp_formats_convert_directly() {
+        assert_eq!(
+            EncodedImageFormat::try_from(RustThumbnailFormat::Jpeg { quality: 80 }).unwrap(),
+            EncodedImageFormat::Jpeg { quality: 80 }
+        );
+        assert_eq!(
+            EncodedImageFormat::try_from(RustThumbnailFormat::WebP { quality: 80 }).unwrap(),
+            EncodedImageFormat::WebP { quality: 80 }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "avif")]
+    fn avif_format_converts_when_feature_is_enabled() {
+        assert_eq!(
+            EncodedImageFormat::try_from(RustThumbnailFormat::Avif {
+                quality: 70,
+                speed: 6
+            })
+            .unwrap(),
+            EncodedImageFormat::Avif {
+                quality: 70,
+                speed: 6
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "avif"))]
+    fn avif_format_errors_instead_of_silently_falling_back_to_jpeg() {
+        let result = EncodedImageFormat::try_from(RustThumbnailFormat::Avif {
+            quality: 70,
+            speed: 6,
+        });
+        assert!(
+            result.is_err(),
+            "AVIF conversion must fail loudly without the `avif` feature, not silently swap codecs"
+        );
+    }
+}
@@ -1,9 +1,16 @@
 use crate::image::decode::decode_image_from_path;
+use crate::image::image_compression::compute_blurhash;
 use crate::ml::{
     clip::image::run_clip_image,
+    embedding_cache::EmbeddingCacheConfig,
     error::{MlError, MlResult},
-    face::{align::run_face_alignment, detect::run_face_detection, embed::run_face_embedding},
-    runtime::{self, ExecutionProviderPolicy, MlRuntimeConfig, ModelPaths},
+    face::{
+        align::run_face_alignment,
+        detect::{NmsConfig, run_face_detection},
+        embed::run_face_embedding,
+    },
+    runtime::{self, ExecutionProviderPolicy, LensDistortionConfig, MlRuntimeConfig, ModelPaths},
+    undistort::correct_lens_distortion,
 };
 
 #[derive(Clone, Debug)]
@@ -32,10 +39,24 @@ pub struct RustModelPaths {
     pub clip_image: String,
 }
 
+/// Brown–Conrady lens-distortion coefficients surfaced to the Dart layer. The default is
+/// the identity (all zero), so callers that don't set it get the existing behavior.
+#[derive(Clone, Debug, Default)]
+pub struct RustLensDistortion {
+    pub k1: f64,
+    pub k2: f64,
+    pub k3: f64,
+    pub p1: f64,
+    pub p2: f64,
+    pub principal_point: Option<Vec<f64>>,
+    pub focal: Option<Vec<f64>>,
+}
+
 #[derive(Clone, Debug)]
 pub struct RustMlRuntimeConfig {
     pub model_paths: RustModelPaths,
     pub provider_policy: RustExecutionProviderPolicy,
+    pub lens_distortion: RustLensDistortion,
 }
 
 #[derive(Clone, Debug)]
@@ -46,6 +67,7 @@ pub struct AnalyzeImageRequest {
     pub run_clip: bool,
     pub model_paths: RustModelPaths,
     pub provider_policy: RustExecutionProviderPolicy,
+    pub lens_distortion: RustLensDistortion,
 }
 
 #[derive(Clone, Debug)]
@@ -67,6 +89,10 @@ pub struct RustAlignmentResult {
     pub center: Vec<f64>,
     pub size: f64,
     pub rotation: f64,
+    pub inlier_count: u32,
+    pub yaw: f64,
+    pub pitch: f64,
+    pub roll: f64,
 }
 
 #[derive(Clone, Debug)]
@@ -76,6 +102,7 @@ pub struct RustFaceResult {
     pub alignment: RustAlignmentResult,
     pub embedding: Vec<f64>,
     pub face_id: String,
+    pub blurhash: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -89,6 +116,7 @@ pub struct AnalyzeImageResult {
     pub decoded_image_size: RustDimensions,
     pub faces: Option<Vec<RustFaceResult>>,
     pub clip: Option<RustClipResult>,
+    pub blurhash: Option<String>,
 }
 
 pub fn init_ml_runtime(config: RustMlRuntimeConfig) -> Result<(), String> {
@@ -109,22 +137,39 @@ fn analyze_image_rust_inner(req: AnalyzeImageRequest) -> MlResult<AnalyzeImageRe
     let runtime_config = MlRuntimeConfig {
         model_paths: to_model_paths(&req.model_paths),
         provider_policy: to_provider_policy(&req.provider_policy),
+        lens_distortion: to_lens_distortion(&req.lens_distortion),
+        nms: NmsConfig::default(),
+        embedding_cache: EmbeddingCacheConfig::default(),
     };
 
     let decoded = decode_image_from_path(&req.image_path)?;
+
+    // Undistort only for the face path (detection and alignment); the whole-image BlurHash
+    // and CLIP embedding keep the original geometry. Skipped entirely when no coefficients
+    // are supplied.
+    let face_decoded = if req.run_faces && !runtime_config.lens_distortion.is_identity() {
+        Some(correct_lens_distortion(&decoded, &runtime_config.lens_distortion)?)
+    } else {
+        None
+    };
     let dims = RustDimensions {
         width: decoded.dimensions.width as i32,
         height: decoded.dimensions.height as i32,
     };
+    // A whole-image BlurHash lets clients paint a gradient placeholder before the
+    // full image is available. Treat it as best-effort so a failure here never
+    // blocks the actual face/CLIP analysis.
+    let blurhash = compute_blurhash(&decoded, 4, 3).ok();
 
     runtime::with_runtime_mut(&runtime_config, |runtime| {
         let faces = if req.run_faces {
-            let detections = run_face_detection(runtime, &decoded)?;
+            let face_input = face_decoded.as_ref().unwrap_or(&decoded);
+            let detections = run_face_detection(runtime, face_input, &runtime_config.nms)?;
             if detections.is_empty() {
                 Some(Vec::new())
             } else {
                 let (aligned, mut face_results) =
-                    run_face_alignment(req.file_id, &decoded, &detections)?;
+                    run_face_alignment(req.file_id, face_input, &detections)?;
                 run_face_embedding(runtime, &aligned, &mut face_results)?;
                 Some(face_results.into_iter().map(to_api_face_result).collect())
             }
@@ -146,6 +191,7 @@ fn analyze_image_rust_inner(req: AnalyzeImageRequest) -> MlResult<AnalyzeImageRe
             decoded_image_size: dims.clone(),
             faces,
             clip,
+            blurhash,
         })
     })
 }
@@ -177,6 +223,28 @@ fn to_runtime_config(config: &RustMlRuntimeConfig) -> MlRuntimeConfig {
     MlRuntimeConfig {
         model_paths: to_model_paths(&config.model_paths),
         provider_policy: to_provider_policy(&config.provider_policy),
+        lens_distortion: to_lens_distortion(&config.lens_distortion),
+        nms: NmsConfig::default(),
+        embedding_cache: EmbeddingCacheConfig::default(),
+    }
+}
+
+fn to_lens_distortion(distortion: &RustLensDistortion) -> LensDistortionConfig {
+    fn to_pair(values: &Option<Vec<f64>>) -> Option<(f32, f32)> {
+        values
+            .as_ref()
+            .filter(|v| v.len() == 2)
+            .map(|v| (v[0] as f32, v[1] as f32))
+    }
+
+    LensDistortionConfig {
+        k1: distortion.k1 as f32,
+        k2: distortion.k2 as f32,
+        k3: distortion.k3 as f32,
+        p1: distortion.p1 as f32,
+        p2: distortion.p2 as f32,
+        principal_point: to_pair(&distortion.principal_point),
+        focal: to_pair(&distortion.focal),
     }
 }
 
@@ -230,8 +298,13 @@ fn to_api_face_result(result: crate::ml::types::FaceResult) -> RustFaceResult {
                 .collect(),
             size: result.alignment.size as f64,
             rotation: result.alignment.rotation as f64,
+            inlier_count: result.alignment.inlier_count,
+            yaw: result.alignment.yaw as f64,
+            pitch: result.alignment.pitch as f64,
+            roll: result.alignment.roll as f64,
         },
         embedding: result.embedding.into_iter().map(|v| v as f64).collect(),
         face_id: result.face_id,
+        blurhash: result.blurhash,
     }
 }
@@ -1,9 +1,10 @@
 use flutter_rust_bridge::frb;
 use usearch::{Index, IndexOptions, MetricKind, ScalarKind};
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 const FAST_SEARCH_STEP_COUNTS: [usize; 5] = [200, 500, 2000, 5000, 10000];
 static INDEX_SAVE_TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
@@ -12,15 +13,256 @@ type SearchMatch = (Vec<u64>, Vec<f32>);
 type BulkSearchMatch = (Vec<Vec<u64>>, Vec<Vec<f32>>);
 type BulkSearchByKeyMatch = (Vec<u64>, Vec<Vec<u64>>, Vec<Vec<f32>>);
 
+/// Scalar precision used to store vectors in the index. Lower-precision kinds trade a little
+/// recall for a large on-device memory saving, where CLIP/face embeddings dominate RAM.
+/// Maps directly onto usearch's [`ScalarKind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuantizationKind {
+    F32,
+    F16,
+    I8,
+    B1,
+}
+
+impl QuantizationKind {
+    fn to_scalar_kind(self) -> ScalarKind {
+        match self {
+            QuantizationKind::F32 => ScalarKind::F32,
+            QuantizationKind::F16 => ScalarKind::F16,
+            QuantizationKind::I8 => ScalarKind::I8,
+            QuantizationKind::B1 => ScalarKind::B1,
+        }
+    }
+
+    /// Stable identifier persisted in the sidecar so a reload rebuilds with the same kind.
+    fn as_tag(self) -> &'static str {
+        match self {
+            QuantizationKind::F32 => "f32",
+            QuantizationKind::F16 => "f16",
+            QuantizationKind::I8 => "i8",
+            QuantizationKind::B1 => "b1",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag.trim() {
+            "f32" => Some(QuantizationKind::F32),
+            "f16" => Some(QuantizationKind::F16),
+            "i8" => Some(QuantizationKind::I8),
+            "b1" => Some(QuantizationKind::B1),
+            _ => None,
+        }
+    }
+}
+
+/// Explicit HNSW graph parameters, mirroring usearch's `IndexOptions`.
+///
+/// Each field maps directly onto the usearch option of the same name; a value of zero asks
+/// usearch to pick its own default. Raising `connectivity`/`expansion_add` improves recall at
+/// the cost of build time and memory, while `expansion_search` trades query latency for
+/// recall and can be changed per-query without rebuilding the graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexTuning {
+    pub connectivity: usize,
+    pub expansion_add: usize,
+    pub expansion_search: usize,
+}
+
+impl Default for IndexTuning {
+    fn default() -> Self {
+        // All-auto, matching the original hardcoded construction.
+        Self {
+            connectivity: 0,
+            expansion_add: 0,
+            expansion_search: 0,
+        }
+    }
+}
+
+/// Distance metric the index is built with, mirroring usearch's [`MetricKind`].
+///
+/// The choice is pinned at construction and persisted in the sidecar; reopening an index with a
+/// different metric is rejected since the stored graph is only valid for the metric it was built
+/// with. `Ip` expects already-normalized embeddings (the face/CLIP path), `Cos` normalizes
+/// internally so raw embeddings can be compared by angle, and `L2sq` ranks by squared Euclidean
+/// distance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DistanceMetric {
+    Ip,
+    Cos,
+    L2sq,
+}
+
+impl DistanceMetric {
+    fn to_metric_kind(self) -> MetricKind {
+        match self {
+            DistanceMetric::Ip => MetricKind::IP,
+            DistanceMetric::Cos => MetricKind::Cos,
+            DistanceMetric::L2sq => MetricKind::L2sq,
+        }
+    }
+
+    /// Stable identifier persisted in the sidecar so a reload rebuilds with the same metric.
+    fn as_tag(self) -> &'static str {
+        match self {
+            DistanceMetric::Ip => "ip",
+            DistanceMetric::Cos => "cos",
+            DistanceMetric::L2sq => "l2sq",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag.trim() {
+            "ip" => Some(DistanceMetric::Ip),
+            "cos" => Some(DistanceMetric::Cos),
+            "l2sq" => Some(DistanceMetric::L2sq),
+            _ => None,
+        }
+    }
+
+    /// Convert a raw index distance into a `[0, 1]`-style similarity. For `Ip`/`Cos` that is the
+    /// usual `1 - distance`; for `L2sq` on unit-length vectors the squared distance is
+    /// `2 - 2·cos`, so the matching similarity is `1 - distance / 2`.
+    fn distance_to_similarity(self, distance: f32) -> f32 {
+        match self {
+            DistanceMetric::Ip | DistanceMetric::Cos => 1.0 - distance,
+            DistanceMetric::L2sq => 1.0 - distance / 2.0,
+        }
+    }
+
+    /// Inverse of [`distance_to_similarity`](Self::distance_to_similarity): the largest distance
+    /// still counted as at least `minimum_similarity`.
+    fn similarity_to_max_distance(self, minimum_similarity: f32) -> f32 {
+        match self {
+            DistanceMetric::Ip | DistanceMetric::Cos => 1.0 - minimum_similarity,
+            DistanceMetric::L2sq => 2.0 * (1.0 - minimum_similarity),
+        }
+    }
+
+    /// Distance between a query and a stored vector under this metric, so a keyword-only
+    /// candidate pulled into hybrid search is scored exactly like an ANN hit.
+    fn distance(self, query: &[f32], vector: &[f32]) -> f32 {
+        let dot: f32 = query.iter().zip(vector.iter()).map(|(a, b)| a * b).sum();
+        match self {
+            DistanceMetric::Ip => 1.0 - dot,
+            DistanceMetric::Cos => {
+                let qn = query.iter().map(|v| v * v).sum::<f32>().sqrt();
+                let vn = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+                let denom = qn * vn;
+                if denom <= f32::EPSILON {
+                    1.0
+                } else {
+                    1.0 - dot / denom
+                }
+            }
+            DistanceMetric::L2sq => query
+                .iter()
+                .zip(vector.iter())
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum(),
+        }
+    }
+}
+
+/// Controls when index mutations are serialized to disk.
+#[derive(Clone, Copy, Debug)]
+enum AutosavePolicy {
+    /// Persist on every mutation (the original behavior).
+    Immediate,
+    /// Coalesce mutations: a mutation triggers a save once `max_pending_changes` are
+    /// outstanding or once `debounce` has passed since the first unsaved mutation. The check
+    /// runs only when a mutation is recorded; no save is scheduled in the background, so
+    /// pending changes otherwise wait for an explicit [`VectorDB::flush`] or drop.
+    Debounced {
+        max_pending_changes: usize,
+        debounce: Duration,
+    },
+}
+
 #[frb(opaque)]
 pub struct VectorDB {
     index: Index,
     path: PathBuf,
+    quantization: QuantizationKind,
+    tuning: IndexTuning,
+    metric: DistanceMetric,
+    autosave: AutosavePolicy,
+    dirty: bool,
+    pending_changes: usize,
+    dirty_since: Option<Instant>,
+}
+
+impl Drop for VectorDB {
+    fn drop(&mut self) {
+        if self.dirty {
+            if let Err(error) = self.flush() {
+                eprintln!("Failed to flush VectorDB index on drop: {error}");
+            }
+        }
+    }
 }
 
 impl VectorDB {
     #[frb(sync)]
     pub fn new(file_path: &str, dimensions: usize) -> Result<Self, String> {
+        Self::new_quantized(file_path, dimensions, QuantizationKind::F32)
+    }
+
+    /// Like [`new`](Self::new) but stores vectors at the given `quantization`. The chosen
+    /// kind is written to a sidecar next to the index; when an index already exists on disk
+    /// the sidecar's kind takes precedence so the reconstructed options match the stored
+    /// data rather than whatever the caller happened to pass.
+    #[frb(sync)]
+    pub fn new_quantized(
+        file_path: &str,
+        dimensions: usize,
+        quantization: QuantizationKind,
+    ) -> Result<Self, String> {
+        Self::new_tuned(file_path, dimensions, quantization, IndexTuning::default())
+    }
+
+    /// Like [`new_quantized`](Self::new_quantized) but also pins the HNSW graph parameters.
+    ///
+    /// `tuning` controls the accuracy/latency/memory tradeoff: higher `connectivity` and
+    /// `expansion_add` build a denser, slower-to-build graph with better recall, while
+    /// `expansion_search` trades query latency for recall and can later be retuned with
+    /// [`set_expansion_search`](Self::set_expansion_search) without a rebuild. A zero leaves
+    /// usearch to pick its own default for that parameter. The build parameters are persisted
+    /// in the sidecar so a reopened index reports them consistently through
+    /// [`get_index_stats`](Self::get_index_stats).
+    #[frb(sync)]
+    pub fn new_tuned(
+        file_path: &str,
+        dimensions: usize,
+        quantization: QuantizationKind,
+        tuning: IndexTuning,
+    ) -> Result<Self, String> {
+        Self::new_with_metric(
+            file_path,
+            dimensions,
+            quantization,
+            tuning,
+            DistanceMetric::Ip,
+        )
+    }
+
+    /// Like [`new_tuned`](Self::new_tuned) but also selects the distance `metric`.
+    ///
+    /// `Ip` keeps the original inner-product behavior for already-normalized face/CLIP
+    /// embeddings; `Cos` normalizes internally so raw embeddings can be compared by angle; and
+    /// `L2sq` ranks by squared Euclidean distance. The metric is persisted in the sidecar and a
+    /// reopen that requests a different metric is rejected, since the stored graph is only valid
+    /// for the metric it was built with. The similarity/threshold conversions used by
+    /// [`approx_search_vectors_within_similarity`](Self::approx_search_vectors_within_similarity)
+    /// and the hybrid search are chosen to match the active metric.
+    #[frb(sync)]
+    pub fn new_with_metric(
+        file_path: &str,
+        dimensions: usize,
+        quantization: QuantizationKind,
+        tuning: IndexTuning,
+        metric: DistanceMetric,
+    ) -> Result<Self, String> {
         let path = PathBuf::from(file_path);
         let file_exists = path.try_exists().map_err(|e| {
             format!(
@@ -29,20 +271,56 @@ impl VectorDB {
             )
         })?;
 
+        // A pre-existing index was serialized with its own quantization and graph parameters;
+        // honour the sidecar so the reconstructed options match the stored data rather than
+        // whatever the caller happened to pass.
+        let sidecar = if file_exists {
+            read_index_sidecar(&path)
+        } else {
+            IndexSidecar::default()
+        };
+        let quantization = sidecar.quantization.unwrap_or(quantization);
+        let tuning = sidecar.tuning.unwrap_or(tuning);
+        // Unlike quantization and tuning (where the sidecar silently wins), a stored metric that
+        // disagrees with the requested one is an error: the persisted graph is only meaningful
+        // under the metric it was built with, so loading it as another metric would return
+        // silently wrong neighbours.
+        if let Some(stored) = sidecar.metric {
+            if stored != metric {
+                return Err(format!(
+                    "Index at {} was built with metric {} but {} was requested",
+                    path.display(),
+                    stored.as_tag(),
+                    metric.as_tag()
+                ));
+            }
+        }
+
         let mut options = IndexOptions::default();
         options.dimensions = dimensions;
-        options.metric = MetricKind::IP;
-        options.quantization = ScalarKind::F32;
-        options.connectivity = 0; // auto
-        options.expansion_add = 0; // auto
-        options.expansion_search = 0; // auto
+        options.metric = metric.to_metric_kind();
+        options.quantization = quantization.to_scalar_kind();
+        options.connectivity = tuning.connectivity;
+        options.expansion_add = tuning.expansion_add;
+        options.expansion_search = tuning.expansion_search;
 
         let index = Index::new(&options).map_err(|e| format!("Failed to create index: {e}"))?;
         index
             .reserve(1000)
             .map_err(|e| format!("Failed to reserve space in index: {e}"))?;
 
-        let db = Self { index, path };
+        let db = Self {
+            index,
+            path,
+            quantization,
+            tuning,
+            metric,
+            autosave: AutosavePolicy::Immediate,
+            dirty: false,
+            pending_changes: 0,
+            dirty_since: None,
+        };
+        db.write_index_sidecar()?;
 
         if file_exists {
             println!("Loading index from disk.");
@@ -108,6 +386,90 @@ impl VectorDB {
         Ok(())
     }
 
+    /// Switch to debounced autosave. Mutations coalesce: a mutation saves the index once
+    /// `max_pending_changes` are outstanding or once `debounce_ms` have passed since the first
+    /// unsaved mutation, turning a bulk re-index from thousands of full serializations into a
+    /// handful. No timer fires on its own; changes left pending after the last mutation are
+    /// saved by [`flush`](Self::flush) or on drop.
+    pub fn enable_debounced_autosave(&mut self, max_pending_changes: usize, debounce_ms: u64) {
+        self.autosave = AutosavePolicy::Debounced {
+            max_pending_changes: max_pending_changes.max(1),
+            debounce: Duration::from_millis(debounce_ms),
+        };
+    }
+
+    /// Persist the index now if it has pending changes, resetting the dirty state. A no-op
+    /// when nothing is pending.
+    pub fn flush(&mut self) -> Result<(), String> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.save_index()?;
+        self.dirty = false;
+        self.pending_changes = 0;
+        self.dirty_since = None;
+        Ok(())
+    }
+
+    fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.pending_changes += 1;
+        if self.dirty_since.is_none() {
+            self.dirty_since = Some(Instant::now());
+        }
+    }
+
+    /// Record a mutation and save if the active autosave policy calls for it.
+    fn note_mutation(&mut self) -> Result<(), String> {
+        self.mark_dirty();
+        let should_save = match self.autosave {
+            AutosavePolicy::Immediate => true,
+            AutosavePolicy::Debounced {
+                max_pending_changes,
+                debounce,
+            } => {
+                self.pending_changes >= max_pending_changes
+                    || self
+                        .dirty_since
+                        .map(|since| since.elapsed() >= debounce)
+                        .unwrap_or(true)
+            }
+        };
+        if should_save {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn sidecar_path(&self) -> PathBuf {
+        self.path.with_extension("quant")
+    }
+
+    fn write_index_sidecar(&self) -> Result<(), String> {
+        let sidecar = self.sidecar_path();
+        if let Some(parent) = sidecar.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                format!(
+                    "Failed to create sidecar parent directory {}: {e}",
+                    parent.display()
+                )
+            })?;
+        }
+        // One `key value` line per setting. The legacy format was a bare quantization tag, so
+        // `quant` stays on the first line and the reader still accepts the old single-token file.
+        let contents = format!(
+            "quant {}\nmetric {}\nconnectivity {}\nexpansion_add {}\nexpansion_search {}\n",
+            self.quantization.as_tag(),
+            self.metric.as_tag(),
+            self.tuning.connectivity,
+            self.tuning.expansion_add,
+            self.tuning.expansion_search,
+        );
+        std::fs::write(&sidecar, contents).map_err(|e| {
+            format!("Failed to write index sidecar {}: {e}", sidecar.display())
+        })
+    }
+
     fn ensure_capacity(&self, margin: usize) -> Result<(), String> {
         let current_size = self.index.size();
         let capacity = self.index.capacity();
@@ -130,7 +492,7 @@ impl VectorDB {
         self.index
             .add(key, vector)
             .map_err(|e| format!("Failed to add vector for key {key}: {e}"))?;
-        self.save_index()
+        self.note_mutation()
     }
 
     pub fn bulk_add_vectors(&mut self, keys: Vec<u64>, vectors: &[Vec<f32>]) -> Result<(), String> {
@@ -145,7 +507,9 @@ impl VectorDB {
                 .add(*key, vector)
                 .map_err(|e| format!("Failed to bulk add vector for key {key}: {e}"))?;
         }
-        self.save_index()
+        // Bulk paths always persist once at the end, regardless of the autosave policy.
+        self.mark_dirty();
+        self.flush()
     }
 
     pub fn search_vectors(
@@ -166,6 +530,77 @@ impl VectorDB {
         Ok((matches.keys, matches.distances))
     }
 
+    /// Fuse a semantic ANN query with externally supplied keyword relevance scores.
+    ///
+    /// Runs the usual ANN search for `query`, converts each candidate's distance to a
+    /// semantic similarity for the active metric (`1.0 - distance` for `Ip`/`Cos`,
+    /// `1.0 - distance / 2` for `L2sq`), and blends it with the caller's keyword
+    /// score using the convex combination `alpha * semantic + (1 - alpha) * keyword`. Both
+    /// score lists are min-max renormalized to `[0, 1]` before blending so neither side
+    /// dominates by scale. Keys that appear only in `keyword_scores` but are present in the
+    /// index are pulled in with an exact distance so they can still win on the keyword term.
+    /// Returns the fused keys and their scores sorted by descending fused score, at most
+    /// `count` of them.
+    pub fn hybrid_search_vectors(
+        &self,
+        query: &[f32],
+        keyword_scores: &[(u64, f32)],
+        count: usize,
+        alpha: f32,
+    ) -> Result<SearchMatch, String> {
+        if count == 0 || self.index.size() == 0 {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        let alpha = alpha.clamp(0.0, 1.0);
+
+        // Semantic candidates from the ANN query, keyed for fast lookup/merge.
+        let matches = self
+            .index
+            .search(query, count)
+            .map_err(|e| format!("Failed to search vectors for hybrid query: {e}"))?;
+        let mut semantic: Vec<(u64, f32)> = matches
+            .keys
+            .iter()
+            .copied()
+            .zip(matches.distances.iter().copied())
+            .map(|(key, distance)| (key, self.metric.distance_to_similarity(distance)))
+            .collect();
+        let mut seen: HashSet<u64> = semantic.iter().map(|(key, _)| *key).collect();
+
+        // Pull in keyword-only keys that live in the index so fusion can still surface them.
+        for (key, _) in keyword_scores {
+            if seen.contains(key) || !self.index.contains(*key) {
+                continue;
+            }
+            let vector = self.get_vector(*key)?;
+            let distance = self.metric.distance(query, &vector);
+            semantic.push((*key, self.metric.distance_to_similarity(distance)));
+            seen.insert(*key);
+        }
+
+        let keyword: HashMap<u64, f32> = keyword_scores.iter().copied().collect();
+        let semantic_norm = normalize_scores(semantic.iter().map(|(_, s)| *s), 1.0);
+        let keyword_norm = normalize_scores(
+            semantic
+                .iter()
+                .map(|(key, _)| keyword.get(key).copied().unwrap_or(0.0)),
+            0.0,
+        );
+
+        let mut fused: Vec<(u64, f32)> = semantic
+            .iter()
+            .zip(semantic_norm.iter())
+            .zip(keyword_norm.iter())
+            .map(|(((key, _), semantic), keyword)| {
+                (*key, alpha * *semantic + (1.0 - alpha) * *keyword)
+            })
+            .collect();
+        fused.sort_by(|a, b| b.1.total_cmp(&a.1));
+        fused.truncate(count);
+
+        Ok(fused.into_iter().unzip())
+    }
+
     pub fn approx_search_vectors_within_similarity(
         &self,
         query: &[f32],
@@ -176,7 +611,7 @@ impl VectorDB {
             return Ok((Vec::new(), Vec::new()));
         }
 
-        let max_distance = 1.0_f32 - minimum_similarity;
+        let max_distance = self.metric.similarity_to_max_distance(minimum_similarity);
         if !max_distance.is_finite() || max_distance < 0.0 {
             return Ok((Vec::new(), Vec::new()));
         }
@@ -430,7 +865,7 @@ impl VectorDB {
             .index
             .remove(key)
             .map_err(|e| format!("Failed to remove vector for key {key}: {e}"))?;
-        self.save_index()?;
+        self.note_mutation()?;
         Ok(removed_count)
     }
 
@@ -442,7 +877,8 @@ impl VectorDB {
                 .remove(key)
                 .map_err(|e| format!("Failed to bulk remove vector for key {key}: {e}"))?;
         }
-        self.save_index()?;
+        self.mark_dirty();
+        self.flush()?;
         Ok(removed_count)
     }
 
@@ -453,20 +889,40 @@ impl VectorDB {
         self.index
             .reserve(1000)
             .map_err(|e| format!("Failed to reserve space in index after reset: {e}"))?;
-        self.save_index()
+        self.mark_dirty();
+        self.flush()
     }
 
-    pub fn delete_index(self) -> Result<(), String> {
+    pub fn delete_index(mut self) -> Result<(), String> {
+        // Drop the dirty flag first so the Drop impl doesn't resurrect the file we're about
+        // to delete by flushing on the way out.
+        self.dirty = false;
         if self.path.exists() {
             std::fs::remove_file(&self.path)
                 .map_err(|e| format!("Failed to delete index file {}: {e}", self.path.display()))?;
         } else {
             println!("Index file does not exist.");
         }
+        // Best-effort: drop the quantization sidecar too so a later index at the same path
+        // doesn't inherit a stale kind.
+        let _ = std::fs::remove_file(self.sidecar_path());
         Ok(())
     }
 
-    pub fn get_index_stats(&self) -> (usize, usize, usize, usize, usize, usize, usize) {
+    /// Adjust the search-time expansion without rebuilding the graph. Higher values widen the
+    /// search beam, improving recall at the cost of query latency; this is the knob to dial up
+    /// for face clustering and down for fast on-device autocomplete.
+    pub fn set_expansion_search(&mut self, expansion_search: usize) -> Result<(), String> {
+        self.index
+            .change_expansion_search(expansion_search)
+            .map_err(|e| format!("Failed to set expansion_search: {e}"))?;
+        self.tuning.expansion_search = expansion_search;
+        self.write_index_sidecar()
+    }
+
+    pub fn get_index_stats(
+        &self,
+    ) -> (usize, usize, usize, usize, usize, usize, usize, usize, QuantizationKind) {
         let size = self.index.size();
         let capacity = self.index.capacity();
         let dimensions = self.index.dimensions();
@@ -477,14 +933,278 @@ impl VectorDB {
         let expansion_add = self.index.expansion_add();
         let expansion_search = self.index.expansion_search();
 
+        // Connectivity can't change after build, so report the persisted build parameter for a
+        // consistent value across reopens.
+        let connectivity = self.tuning.connectivity;
+
         (
             size,
             capacity,
             dimensions,
             file_size,
             memory_usage,
+            connectivity,
             expansion_add,
             expansion_search,
+            self.quantization,
+        )
+    }
+}
+
+/// Settings recorded alongside an index so a reopen reconstructs identical options. Either
+/// field is `None` when the sidecar is absent or doesn't record it.
+#[derive(Default)]
+struct IndexSidecar {
+    quantization: Option<QuantizationKind>,
+    tuning: Option<IndexTuning>,
+    metric: Option<DistanceMetric>,
+}
+
+/// Parse the sidecar next to an index. Accepts both the current `key value` lines and the
+/// legacy single-token quantization file; anything missing is left as `None`.
+fn read_index_sidecar(index_path: &std::path::Path) -> IndexSidecar {
+    let sidecar = index_path.with_extension("quant");
+    let Ok(contents) = std::fs::read_to_string(sidecar) else {
+        return IndexSidecar::default();
+    };
+
+    let mut result = IndexSidecar::default();
+    let mut tuning = IndexTuning::default();
+    let mut saw_tuning = false;
+    for line in contents.lines() {
+        let mut parts = line.split_whitespace();
+        let Some(key) = parts.next() else {
+            continue;
+        };
+        let value = parts.next();
+        match (key, value) {
+            ("quant", Some(tag)) => result.quantization = QuantizationKind::from_tag(tag),
+            ("metric", Some(tag)) => result.metric = DistanceMetric::from_tag(tag),
+            ("connectivity", Some(v)) => {
+                if let Ok(n) = v.parse() {
+                    tuning.connectivity = n;
+                    saw_tuning = true;
+                }
+            }
+            ("expansion_add", Some(v)) => {
+                if let Ok(n) = v.parse() {
+                    tuning.expansion_add = n;
+                    saw_tuning = true;
+                }
+            }
+            ("expansion_search", Some(v)) => {
+                if let Ok(n) = v.parse() {
+                    tuning.expansion_search = n;
+                    saw_tuning = true;
+                }
+            }
+            // Legacy sidecar: a lone quantization tag with no key.
+            (tag, None) => {
+                if let Some(kind) = QuantizationKind::from_tag(tag) {
+                    result.quantization = Some(kind);
+                }
+            }
+            _ => {}
+        }
+    }
+    if saw_tuning {
+        result.tuning = Some(tuning);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_PATH_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    fn temp_index_path() -> PathBuf {
+        let sequence = TEST_PATH_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!(
+            "vectordb_quant_test_{}_{}.usearch",
+            std::process::id(),
+            sequence
+        ))
+    }
+
+    // Deterministic unit-length pseudo-vector so the test needs no rng dependency and the
+    // synthetic dataset is stable across runs.
+    fn pseudo_vector(seed: u64, dims: usize) -> Vec<f32> {
+        let mut state = seed
+            .wrapping_mul(2862933555777941757)
+            .wrapping_add(3037000493);
+        let mut values: Vec<f32> = (0..dims)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (((state >> 33) as f32) / ((1u64 << 31) as f32)) - 1.0
+            })
+            .collect();
+        let norm = values.iter().map(|v| v * v).sum::<f32>().sqrt().max(1e-6);
+        for value in values.iter_mut() {
+            *value /= norm;
+        }
+        values
+    }
+
+    fn build_index(quantization: QuantizationKind, dims: usize, count: u64) -> VectorDB {
+        let path = temp_index_path();
+        let mut db = VectorDB::new_quantized(path.to_str().unwrap(), dims, quantization)
+            .expect("index should build");
+        let keys: Vec<u64> = (0..count).collect();
+        let vectors: Vec<Vec<f32>> = keys.iter().map(|k| pseudo_vector(*k, dims)).collect();
+        db.bulk_add_vectors(keys, &vectors)
+            .expect("bulk add should succeed");
+        db
+    }
+
+    #[test]
+    fn quantization_is_persisted_in_sidecar_and_stats() {
+        let path = temp_index_path();
+        {
+            let db = VectorDB::new_quantized(path.to_str().unwrap(), 8, QuantizationKind::F16)
+                .expect("index should build");
+            assert_eq!(db.get_index_stats().8, QuantizationKind::F16);
+        }
+        // Reopening with a different requested kind must defer to the sidecar.
+        let reopened = VectorDB::new_quantized(path.to_str().unwrap(), 8, QuantizationKind::F32)
+            .expect("reopen should succeed");
+        assert_eq!(reopened.get_index_stats().8, QuantizationKind::F16);
+        reopened.delete_index().expect("cleanup should succeed");
+    }
+
+    #[test]
+    fn metric_is_persisted_and_mismatch_is_rejected() {
+        let path = temp_index_path();
+        {
+            VectorDB::new_with_metric(
+                path.to_str().unwrap(),
+                8,
+                QuantizationKind::F32,
+                IndexTuning::default(),
+                DistanceMetric::Cos,
+            )
+            .expect("index should build");
+        }
+        // Reopening with the stored metric succeeds; a different metric is rejected.
+        VectorDB::new_with_metric(
+            path.to_str().unwrap(),
+            8,
+            QuantizationKind::F32,
+            IndexTuning::default(),
+            DistanceMetric::Cos,
+        )
+        .expect("reopen with matching metric should succeed");
+        let mismatch = VectorDB::new_with_metric(
+            path.to_str().unwrap(),
+            8,
+            QuantizationKind::F32,
+            IndexTuning::default(),
+            DistanceMetric::L2sq,
+        );
+        assert!(mismatch.is_err(), "reopen with a different metric must fail");
+
+        let reopened = VectorDB::new_with_metric(
+            path.to_str().unwrap(),
+            8,
+            QuantizationKind::F32,
+            IndexTuning::default(),
+            DistanceMetric::Cos,
         )
+        .expect("reopen should succeed");
+        reopened.delete_index().expect("cleanup should succeed");
+    }
+
+    #[test]
+    fn f16_recall_stays_close_to_f32() {
+        const DIMS: usize = 32;
+        const COUNT: u64 = 256;
+        const TOP_K: usize = 10;
+        const QUERIES: u64 = 24;
+
+        let f32_db = build_index(QuantizationKind::F32, DIMS, COUNT);
+        let f16_db = build_index(QuantizationKind::F16, DIMS, COUNT);
+
+        let mut total_recall = 0.0f32;
+        for q in 0..QUERIES {
+            let query = pseudo_vector(COUNT + q, DIMS);
+            let (f32_keys, _) = f32_db.search_vectors(&query, TOP_K, false).unwrap();
+            let (f16_keys, _) = f16_db.search_vectors(&query, TOP_K, false).unwrap();
+            let reference: HashSet<u64> = f32_keys.into_iter().collect();
+            let overlap = f16_keys.iter().filter(|k| reference.contains(k)).count();
+            total_recall += overlap as f32 / TOP_K as f32;
+        }
+        let recall = total_recall / QUERIES as f32;
+
+        // F16 halves memory; recall should still track F32 closely on this synthetic set.
+        assert!(
+            recall >= 0.8,
+            "f16 recall {recall} degraded more than the 0.8 bound"
+        );
+
+        f32_db.delete_index().unwrap();
+        f16_db.delete_index().unwrap();
+    }
+
+    #[test]
+    fn hybrid_search_with_no_keyword_overlap_does_not_inflate_scores() {
+        const DIMS: usize = 8;
+        const COUNT: u64 = 16;
+
+        let db = build_index(QuantizationKind::F32, DIMS, COUNT);
+        let query = pseudo_vector(COUNT, DIMS);
+
+        // No keyword pass has run yet: every semantic candidate is absent from
+        // `keyword_scores`, so the keyword side is all zeros and must normalize to 0.0,
+        // not 1.0. The fused score should then equal exactly `alpha * semantic`.
+        let alpha = 0.5;
+        let (fused_keys, fused_scores) = db
+            .hybrid_search_vectors(&query, &[], 5, alpha)
+            .expect("hybrid search should succeed");
+        let (semantic_keys, semantic_distances) =
+            db.search_vectors(&query, 5, false).expect("search should succeed");
+        assert_eq!(fused_keys, semantic_keys);
+
+        let semantic_similarities: Vec<f32> = semantic_distances
+            .iter()
+            .map(|d| db.metric.distance_to_similarity(*d))
+            .collect();
+        let min = semantic_similarities
+            .iter()
+            .copied()
+            .fold(f32::INFINITY, f32::min);
+        let max = semantic_similarities
+            .iter()
+            .copied()
+            .fold(f32::NEG_INFINITY, f32::max);
+        let range = (max - min).max(f32::EPSILON);
+        for (fused, similarity) in fused_scores.iter().zip(semantic_similarities.iter()) {
+            let expected = alpha * (similarity - min) / range;
+            assert!(
+                (fused - expected).abs() < 1e-5,
+                "fused score {fused} should equal alpha*semantic ({expected}) with no keyword signal"
+            );
+        }
+
+        db.delete_index().unwrap();
+    }
+}
+
+/// Min-max scale an iterator of scores into `[0, 1]`. A degenerate range (all values equal)
+/// maps every score to `default_for_degenerate` instead of dividing by zero: callers with a
+/// genuine but flat signal (e.g. a single semantic candidate) should pass `1.0` so it still
+/// contributes fully, while callers whose flatness means "no signal at all" (e.g. keyword
+/// scores that are all the `0.0` default because nothing matched) should pass `0.0` so that
+/// absence doesn't masquerade as uniform relevance.
+fn normalize_scores(scores: impl Iterator<Item = f32>, default_for_degenerate: f32) -> Vec<f32> {
+    let values: Vec<f32> = scores.collect();
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+    if range <= 0.0 {
+        return vec![default_for_degenerate; values.len()];
     }
+    values.iter().map(|v| (v - min) / range).collect()
 }
@@ -1,51 +1,157 @@
-use std::{ffi::OsStr, fs::File, io::BufReader, path::Path, sync::Once};
+use std::{ffi::OsStr, io::Cursor, sync::Once};
 
 use exif::{In, Reader as ExifReader, Tag};
-use image::{DynamicImage, ImageReader, hooks::decoding_hook_registered};
+use image::{
+    ColorType, DynamicImage, ImageDecoder, ImageFormat, ImageReader,
+    codecs::tiff::TiffDecoder, hooks::decoding_hook_registered,
+};
 use libheic_rs::{
-    DecodeGuardrails, exif_orientation_hint_from_path,
-    image_integration::{
-        apply_exif_orientation_dynamic, register_image_decoder_hooks_with_guardrails,
-    },
-    path_extension_is_heif,
+    DecodeGuardrails,
+    image_integration::register_image_decoder_hooks_with_guardrails,
+    isobmff::{PrimaryItemTransformProperty, parse_primary_item_transform_properties},
 };
 
 use crate::ml::{
     error::{MlError, MlResult},
-    types::{DecodedImage, Dimensions},
+    types::{DecodedImage, DecodedImage16, Dimensions},
 };
 
 static IMAGE_DECODER_HOOKS_INIT: Once = Once::new();
 
-pub fn decode_image_from_path(image_path: &str) -> MlResult<DecodedImage> {
-    let decoded_dynamic = decode_with_image_crate(image_path)?;
-    let oriented = orient_decoded_image(decoded_dynamic, image_path).to_rgb8();
+/// Decode-time resource ceilings applied at the crate boundary. These back both the
+/// HEIF/AVIF hook registration and the first-class TIFF guardrail, so every format that
+/// can declare very large dimensions is bounded identically.
+const MAX_INPUT_BYTES: u64 = 128 * 1024 * 1024;
+const MAX_DECODE_PIXELS: u64 = 256_000_000;
+const MAX_TEMP_SPOOL_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Decode an image from an in-memory blob. This is the single decode entry point, so
+/// callers can decode already-downloaded bytes without spooling to a temp file first.
+///
+/// Orientation is resolved from one source of truth: if the HEIF primary item already
+/// carries an `irot`/`imir` transform (which the decoder applies), the Exif orientation
+/// is suppressed to avoid double-rotating the image; otherwise the Exif orientation is
+/// applied as usual.
+pub fn decode_image_from_bytes(file_bytes: &[u8]) -> MlResult<DecodedImage> {
+    // Record the source precision before `to_rgb8()` quantizes 16-bit PNG/TIFF and HDR
+    // float sources down to 8-bit, so callers know whether precision was reduced.
+    let (oriented, source_bit_depth) = decode_oriented_dynamic(file_bytes)?;
+    let rgb = oriented.to_rgb8();
 
     Ok(DecodedImage {
         dimensions: Dimensions {
-            width: oriented.width(),
-            height: oriented.height(),
+            width: rgb.width(),
+            height: rgb.height(),
         },
-        rgb: oriented.into_raw(),
+        rgb: rgb.into_raw(),
+        source_bit_depth,
     })
 }
 
-fn decode_with_image_crate(image_path: &str) -> MlResult<DynamicImage> {
+/// Thin wrapper over [`decode_image_from_bytes`] that reads the file into memory first.
+pub fn decode_image_from_path(image_path: &str) -> MlResult<DecodedImage> {
+    let file_bytes = std::fs::read(image_path)
+        .map_err(|e| MlError::Decode(format!("failed to read image file '{image_path}': {e}")))?;
+    decode_image_from_bytes(&file_bytes)
+}
+
+/// HDR-aware decode that keeps 16-bit precision in a [`DecodedImage16`] instead of
+/// quantizing at decode time. 8-bit sources are widened to `u16` (so the resize path can
+/// stay uniform); `source_bit_depth` records whether the widening added real bits.
+pub fn decode_image16_from_bytes(file_bytes: &[u8]) -> MlResult<DecodedImage16> {
+    let (oriented, source_bit_depth) = decode_oriented_dynamic(file_bytes)?;
+    let rgb = oriented.to_rgb16();
+
+    Ok(DecodedImage16 {
+        dimensions: Dimensions {
+            width: rgb.width(),
+            height: rgb.height(),
+        },
+        rgb: rgb.into_raw(),
+        source_bit_depth,
+    })
+}
+
+/// Thin wrapper over [`decode_image16_from_bytes`] that reads the file into memory first.
+pub fn decode_image16_from_path(image_path: &str) -> MlResult<DecodedImage16> {
+    let file_bytes = std::fs::read(image_path)
+        .map_err(|e| MlError::Decode(format!("failed to read image file '{image_path}': {e}")))?;
+    decode_image16_from_bytes(&file_bytes)
+}
+
+/// Decode a blob, resolve orientation from the single source of truth, and return the
+/// oriented [`DynamicImage`] together with its source per-channel bit depth. Both the
+/// 8-bit and 16-bit entry points build on this so they stay behaviourally identical.
+fn decode_oriented_dynamic(file_bytes: &[u8]) -> MlResult<(DynamicImage, u8)> {
+    let exif_orientation = read_exif_orientation(file_bytes);
+    let should_apply_exif_orientation =
+        should_apply_exif_orientation(file_bytes, exif_orientation);
+
+    let decoded_dynamic = decode_with_image_crate(file_bytes)?;
+    let source_bit_depth = channel_bit_depth(decoded_dynamic.color());
+    let oriented = if should_apply_exif_orientation {
+        apply_exif_orientation(decoded_dynamic, exif_orientation)
+    } else {
+        decoded_dynamic
+    };
+
+    Ok((oriented, source_bit_depth))
+}
+
+fn decode_with_image_crate(file_bytes: &[u8]) -> MlResult<DynamicImage> {
     init_image_decoders();
 
-    let reader = ImageReader::open(image_path)
-        .map_err(|e| MlError::Decode(format!("failed to open image file '{image_path}': {e}")))?
+    // `with_guessed_format` + the registered codec hooks cover the full `image` codec
+    // set (JPEG/PNG, 16-bit PNG, TIFF with deflate/LZW/packbits, gray+alpha, HEIF/AVIF),
+    // so multi-format and high-bit-depth inputs decode here instead of failing or
+    // silently truncating.
+    let reader = ImageReader::new(Cursor::new(file_bytes))
         .with_guessed_format()
         .map_err(|e| MlError::Decode(format!("failed to guess image format: {e}")))?;
+
+    // Ordinary formats otherwise skip the guardrails the HEIF/AVIF hooks enforce. TIFF is
+    // the common vector for a decompression bomb (LZW/Deflate/PackBits strips can expand to
+    // a multi-gigapixel frame), so bound it by the declared IFD dimensions before the
+    // decoder allocates any pixel storage.
+    if reader.format() == Some(ImageFormat::Tiff) {
+        enforce_tiff_guardrails(file_bytes)?;
+    }
+
     Ok(reader.decode()?)
 }
 
+/// Reject a TIFF whose declared dimensions exceed [`MAX_DECODE_PIXELS`], or whose encoded
+/// size exceeds [`MAX_INPUT_BYTES`], before any pixel buffer is allocated. Reading the IFD
+/// header is cheap; this mirrors the ceilings the HEIF path already enforces via
+/// [`DecodeGuardrails`].
+fn enforce_tiff_guardrails(file_bytes: &[u8]) -> MlResult<()> {
+    if file_bytes.len() as u64 > MAX_INPUT_BYTES {
+        return Err(MlError::Decode(format!(
+            "TIFF input of {} bytes exceeds the {MAX_INPUT_BYTES}-byte decode limit",
+            file_bytes.len()
+        )));
+    }
+
+    let decoder = TiffDecoder::new(Cursor::new(file_bytes))
+        .map_err(|e| MlError::Decode(format!("failed to read TIFF header: {e}")))?;
+    let (width, height) = decoder.dimensions();
+    let pixels = u64::from(width) * u64::from(height);
+    if pixels > MAX_DECODE_PIXELS {
+        return Err(MlError::Decode(format!(
+            "TIFF dimensions {width}x{height} ({pixels} pixels) exceed the \
+             {MAX_DECODE_PIXELS}-pixel decode limit"
+        )));
+    }
+
+    Ok(())
+}
+
 fn init_image_decoders() {
     IMAGE_DECODER_HOOKS_INIT.call_once(|| {
         let registration = register_image_decoder_hooks_with_guardrails(DecodeGuardrails {
-            max_input_bytes: Some(128 * 1024 * 1024),
-            max_pixels: Some(256_000_000),
-            max_temp_spool_bytes: Some(256 * 1024 * 1024),
+            max_input_bytes: Some(MAX_INPUT_BYTES as usize),
+            max_pixels: Some(MAX_DECODE_PIXELS as usize),
+            max_temp_spool_bytes: Some(MAX_TEMP_SPOOL_BYTES as usize),
             temp_spool_directory: None,
         });
 
@@ -83,49 +189,148 @@ fn init_image_decoders() {
     });
 }
 
-fn orient_decoded_image(image: DynamicImage, image_path: &str) -> DynamicImage {
-    let path = Path::new(image_path);
-    if path_extension_is_heif(path) {
-        return apply_heif_exif_orientation_hint(image, path);
+/// Per-channel bit depth of a decoded image's color type. `to_rgb8()` converts 16-bit
+/// samples with correct rounding (not a naive byte truncation), so the pixel data is
+/// preserved as faithfully as an 8-bit buffer allows while this flag reports the loss.
+fn channel_bit_depth(color: ColorType) -> u8 {
+    match color {
+        ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8 => 8,
+        ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => 16,
+        ColorType::Rgb32F | ColorType::Rgba32F => 32,
+        _ => 8,
     }
-
-    apply_standard_exif_orientation(image, image_path)
 }
 
-fn apply_heif_exif_orientation_hint(image: DynamicImage, image_path: &Path) -> DynamicImage {
-    let hint = match exif_orientation_hint_from_path(image_path) {
-        Ok(hint) => hint,
-        Err(err) => {
-            eprintln!(
-                "[ml][decode] failed to inspect HEIF EXIF orientation for '{}': {}",
-                image_path.display(),
-                err
-            );
-            return image;
-        }
-    };
+fn read_exif_orientation(image_data: &[u8]) -> u32 {
+    let mut reader = Cursor::new(image_data);
+    let exif_reader = ExifReader::new();
+    let exif = exif_reader.read_from_container(&mut reader);
+    exif.ok()
+        .and_then(|data| {
+            data.get_field(Tag::Orientation, In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        })
+        .unwrap_or(1)
+}
 
-    if let Some(orientation) = hint.orientation_to_apply() {
-        return apply_exif_orientation_dynamic(image, orientation);
+fn should_apply_exif_orientation(image_data: &[u8], exif_orientation: u32) -> bool {
+    if exif_orientation == 1 {
+        return false;
     }
 
-    image
+    // HEIF decode already applies primary transforms (irot/imir). Applying Exif orientation again
+    // can double-rotate mirrored/rotated files.
+    !heif_primary_transforms_include_orientation(image_data)
+}
+
+fn heif_primary_transforms_include_orientation(image_data: &[u8]) -> bool {
+    let Ok(primary_transforms) = parse_primary_item_transform_properties(image_data) else {
+        return false;
+    };
+
+    primary_transforms.transforms.iter().any(|transform| {
+        matches!(
+            transform,
+            PrimaryItemTransformProperty::Rotation(rotation)
+                if rotation.rotation_ccw_degrees % 360 != 0
+        ) || matches!(transform, PrimaryItemTransformProperty::Mirror(_))
+    })
 }
 
-fn apply_standard_exif_orientation(image: DynamicImage, image_path: &str) -> DynamicImage {
-    match read_exif_orientation_from_path(image_path) {
-        Some(orientation) => apply_exif_orientation_dynamic(image, orientation),
-        None => image,
+fn apply_exif_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.fliph().rotate270(),
+        6 => image.rotate90(),
+        7 => image.fliph().rotate90(),
+        8 => image.rotate270(),
+        _ => image,
     }
 }
 
-fn read_exif_orientation_from_path(image_path: &str) -> Option<u8> {
-    let file = File::open(image_path).ok()?;
-    let mut reader = BufReader::new(file);
-    let exif = ExifReader::new().read_from_container(&mut reader).ok()?;
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageBuffer, ImageFormat, Rgb, RgbImage};
+
+    use super::{
+        channel_bit_depth, decode_image16_from_bytes, decode_image_from_bytes,
+        decode_with_image_crate, enforce_tiff_guardrails,
+    };
+
+    fn encode(dynamic: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        dynamic
+            .write_to(&mut std::io::Cursor::new(&mut bytes), format)
+            .expect("encoding fixture should succeed");
+        bytes
+    }
+
+    #[test]
+    fn decodes_16bit_png_and_reports_bit_depth() {
+        let source: ImageBuffer<Rgb<u16>, Vec<u16>> =
+            ImageBuffer::from_fn(2, 3, |x, y| Rgb([(x as u16) * 30000, (y as u16) * 20000, 65535]));
+        let bytes = encode(&DynamicImage::ImageRgb16(source), ImageFormat::Png);
+
+        let decoded = decode_image_from_bytes(&bytes).expect("16-bit png should decode");
+        assert_eq!(decoded.source_bit_depth, 16);
+        assert_eq!(decoded.dimensions.width, 2);
+        assert_eq!(decoded.dimensions.height, 3);
+        // 65535 in the blue channel round-trips to 255 after correct 16->8 quantization.
+        assert_eq!(decoded.rgb[2], 255);
+    }
+
+    #[test]
+    fn decodes_tiff_to_expected_dimensions_and_pixels() {
+        let source: RgbImage =
+            ImageBuffer::from_fn(4, 2, |x, _| Rgb([(x as u8) * 10, 128, 200]));
+        let bytes = encode(&DynamicImage::ImageRgb8(source), ImageFormat::Tiff);
+
+        let decoded = decode_image_from_bytes(&bytes).expect("tiff should decode");
+        assert_eq!(decoded.source_bit_depth, 8);
+        assert_eq!(decoded.dimensions.width, 4);
+        assert_eq!(decoded.dimensions.height, 2);
+        // Last column pixel (x=3) is [30, 128, 200].
+        let last = ((3) * 3) as usize;
+        assert_eq!(&decoded.rgb[last..last + 3], &[30, 128, 200]);
+    }
+
+    #[test]
+    fn decode16_retains_full_precision_from_16bit_png() {
+        let source: ImageBuffer<Rgb<u16>, Vec<u16>> =
+            ImageBuffer::from_fn(2, 2, |x, _| Rgb([40_000 + x as u16 * 1000, 12_345, 65_535]));
+        let bytes = encode(&DynamicImage::ImageRgb16(source), ImageFormat::Png);
 
-    exif.get_field(Tag::Orientation, In::PRIMARY)
-        .and_then(|field| field.value.get_uint(0))
-        .and_then(|value| u8::try_from(value).ok())
-        .filter(|value| (1..=8).contains(value))
+        let decoded = decode_image16_from_bytes(&bytes).expect("16-bit png should decode");
+        assert_eq!(decoded.source_bit_depth, 16);
+        // The exact 16-bit samples survive, unlike the 8-bit path which would clamp them.
+        assert_eq!(&decoded.rgb[0..3], &[40_000, 12_345, 65_535]);
+        // Quantizing back down matches the dedicated 8-bit decode.
+        let eight = decode_image_from_bytes(&bytes).expect("8-bit decode should succeed");
+        assert_eq!(decoded.to_rgb8().rgb, eight.rgb);
+    }
+
+    #[test]
+    fn tiff_within_limits_passes_guardrail_and_oversized_header_is_rejected() {
+        let source: RgbImage = ImageBuffer::from_fn(4, 4, |x, y| Rgb([x as u8, y as u8, 7]));
+        let bytes = encode(&DynamicImage::ImageRgb8(source), ImageFormat::Tiff);
+        enforce_tiff_guardrails(&bytes).expect("a small TIFF should pass the guardrail");
+
+        // A non-TIFF blob can't have its header parsed, which is itself a rejection.
+        assert!(enforce_tiff_guardrails(b"not a tiff at all").is_err());
+    }
+
+    #[test]
+    fn decode_from_bytes_matches_raw_decode() {
+        let source: RgbImage = ImageBuffer::from_fn(3, 3, |x, y| Rgb([x as u8, y as u8, 40]));
+        let bytes = encode(&DynamicImage::ImageRgb8(source), ImageFormat::Png);
+
+        let decoded = decode_image_from_bytes(&bytes).expect("png should decode");
+        let raw = decode_with_image_crate(&bytes)
+            .expect("raw decode should succeed")
+            .to_rgb8();
+        assert_eq!(decoded.rgb, raw.into_raw());
+        assert_eq!(channel_bit_depth(image::ColorType::Rgb8), 8);
+    }
 }
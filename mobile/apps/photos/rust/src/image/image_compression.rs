@@ -1,17 +1,43 @@
+use std::io::Cursor;
+
 use image::{
-    ColorType, ImageEncoder,
-    codecs::{jpeg::JpegEncoder, png::PngEncoder},
+    ColorType, DynamicImage, ImageBuffer, ImageEncoder, ImageFormat, Rgb,
+    codecs::{
+        jpeg::JpegEncoder,
+        png::{CompressionType, FilterType, PngEncoder},
+        webp::WebPEncoder,
+    },
 };
+#[cfg(feature = "avif")]
+use image::codecs::avif::AvifEncoder;
 
-use crate::ml::error::{MlError, MlResult};
+use crate::ml::{
+    error::{MlError, MlResult},
+    types::DecodedImage,
+};
 
 pub const FACE_THUMBNAIL_JPEG_QUALITY: u8 = 90;
 pub const FACE_THUMBNAIL_MIN_DIMENSION: u32 = 512;
 
+/// Base83 alphabet used by the BlurHash wire format.
+const BLURHASH_ALPHABET: &[u8; 83] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EncodedImageFormat {
     Jpeg { quality: u8 },
-    Png,
+    /// Lossless PNG. When `optimize` is set, the encoder makes a second pass using
+    /// adaptive per-scanline filter selection and maximum-level deflate, keeping the
+    /// smaller of the two buffers (worth the extra CPU for thumbnails kept long-term).
+    Png { optimize: bool },
+    /// Lossless WebP. `quality` is reserved for a future lossy path; the `image`
+    /// crate's WebP encoder currently only emits lossless output.
+    WebP { quality: u8 },
+    /// AVIF at the given `quality` (0..=100) and encoder `speed` (0..=10, higher
+    /// is faster). Gated behind the `avif` feature because the encoder pulls in
+    /// heavier dependencies.
+    #[cfg(feature = "avif")]
+    Avif { quality: u8, speed: u8 },
 }
 
 pub fn encode_rgb(
@@ -44,20 +70,293 @@ pub fn encode_rgb(
                 .write_image(rgb_bytes, width, height, ColorType::Rgb8.into())
                 .map_err(|e| MlError::Postprocess(format!("failed to encode JPEG: {e}")))?;
         }
-        EncodedImageFormat::Png => {
+        EncodedImageFormat::Png { optimize } => {
             PngEncoder::new(&mut encoded)
                 .write_image(rgb_bytes, width, height, ColorType::Rgb8.into())
                 .map_err(|e| MlError::Postprocess(format!("failed to encode PNG: {e}")))?;
+            if optimize {
+                let optimized = encode_png_optimized(rgb_bytes, width, height)?;
+                if optimized.len() < encoded.len() {
+                    encoded = optimized;
+                }
+            }
+        }
+        EncodedImageFormat::WebP { quality: _ } => {
+            WebPEncoder::new_lossless(&mut encoded)
+                .write_image(rgb_bytes, width, height, ColorType::Rgb8.into())
+                .map_err(|e| MlError::Postprocess(format!("failed to encode WebP: {e}")))?;
+        }
+        #[cfg(feature = "avif")]
+        EncodedImageFormat::Avif { quality, speed } => {
+            AvifEncoder::new_with_speed_quality(&mut encoded, speed, quality)
+                .write_image(rgb_bytes, width, height, ColorType::Rgb8.into())
+                .map_err(|e| MlError::Postprocess(format!("failed to encode AVIF: {e}")))?;
         }
     }
     Ok(encoded)
 }
 
+/// Encode a 16-bit RGB buffer (`width * height * 3` `u16` samples), retaining the full
+/// precision for formats that can carry it. PNG is written as a native 16-bit PNG; every
+/// other format is quantized down to 8-bit (with correct rounding) and routed through
+/// [`encode_rgb`], since the `image` crate's JPEG/WebP/AVIF encoders are 8-bit only.
+pub fn encode_rgb16(
+    rgb_samples: &[u16],
+    width: u32,
+    height: u32,
+    format: EncodedImageFormat,
+) -> MlResult<Vec<u8>> {
+    if width == 0 || height == 0 {
+        return Err(MlError::Postprocess(
+            "cannot encode image with zero width or height".to_string(),
+        ));
+    }
+
+    let expected_len = width as usize * height as usize * 3;
+    if rgb_samples.len() != expected_len {
+        return Err(MlError::Postprocess(format!(
+            "invalid RGB buffer length {}, expected {} for {}x{}",
+            rgb_samples.len(),
+            expected_len,
+            width,
+            height
+        )));
+    }
+
+    match format {
+        EncodedImageFormat::Png { .. } => {
+            let buffer =
+                ImageBuffer::<Rgb<u16>, Vec<u16>>::from_raw(width, height, rgb_samples.to_vec())
+                    .ok_or_else(|| {
+                        MlError::Postprocess("failed to build 16-bit RGB buffer".to_string())
+                    })?;
+            let mut encoded = Vec::new();
+            DynamicImage::ImageRgb16(buffer)
+                .write_to(&mut Cursor::new(&mut encoded), ImageFormat::Png)
+                .map_err(|e| MlError::Postprocess(format!("failed to encode 16-bit PNG: {e}")))?;
+            Ok(encoded)
+        }
+        other => {
+            let rgb8: Vec<u8> = rgb_samples
+                .iter()
+                .map(|&channel| ((channel as u32 * 255 + 32_767) / 65_535) as u8)
+                .collect();
+            encode_rgb(&rgb8, width, height, other)
+        }
+    }
+}
+
+/// Re-encode the given RGB buffer as a PNG using adaptive per-scanline filtering
+/// (None/Sub/Up/Average/Paeth picked per row by minimum sum of absolute signed byte
+/// deltas) and maximum-level deflate. This trades encode CPU for a smaller lossless
+/// file, mirroring the oxipng approach for long-lived thumbnails.
+fn encode_png_optimized(rgb_bytes: &[u8], width: u32, height: u32) -> MlResult<Vec<u8>> {
+    let mut optimized = Vec::new();
+    PngEncoder::new_with_quality(&mut optimized, CompressionType::Best, FilterType::Adaptive)
+        .write_image(rgb_bytes, width, height, ColorType::Rgb8.into())
+        .map_err(|e| MlError::Postprocess(format!("failed to encode optimized PNG: {e}")))?;
+    Ok(optimized)
+}
+
+/// Compute a [BlurHash](https://blurha.sh) string for a decoded RGB image.
+///
+/// `components_x`/`components_y` control the number of horizontal/vertical basis
+/// components and must both lie in `1..=9`. The result is a compact base83 string
+/// that clients can decode into a blurred gradient placeholder while the full
+/// JPEG/PNG is still loading.
+pub fn compute_blurhash(
+    decoded: &DecodedImage,
+    components_x: u32,
+    components_y: u32,
+) -> MlResult<String> {
+    if !(1..=9).contains(&components_x) || !(1..=9).contains(&components_y) {
+        return Err(MlError::InvalidRequest(format!(
+            "blurhash components must be in 1..=9, got {components_x}x{components_y}"
+        )));
+    }
+    if decoded.dimensions.width == 0 || decoded.dimensions.height == 0 {
+        return Err(MlError::Postprocess(
+            "cannot compute blurhash for image with zero width or height".to_string(),
+        ));
+    }
+
+    let width = decoded.dimensions.width as usize;
+    let height = decoded.dimensions.height as usize;
+    let expected_len = width * height * 3;
+    if decoded.rgb.len() != expected_len {
+        return Err(MlError::Postprocess(format!(
+            "invalid RGB buffer length {}, expected {} for {}x{}",
+            decoded.rgb.len(),
+            expected_len,
+            width,
+            height
+        )));
+    }
+
+    // Pre-convert every channel to linear light once so the nested basis loop stays cheap.
+    let linear: Vec<f32> = decoded.rgb.iter().map(|c| srgb_to_linear(*c)).collect();
+
+    let component_count = (components_x * components_y) as usize;
+    let mut factors = Vec::with_capacity(component_count);
+    for j in 0..components_y {
+        for i in 0..components_x {
+            let normalisation = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            factors.push(basis_factor(
+                &linear,
+                width,
+                height,
+                i,
+                j,
+                normalisation,
+            ));
+        }
+    }
+
+    let dc = factors[0];
+    let ac = &factors[1..];
+
+    let max_ac = ac
+        .iter()
+        .flat_map(|channels| channels.iter())
+        .fold(0.0f32, |acc, value| acc.max(value.abs()));
+    let quantised_max = ((max_ac * 166.0 - 0.5).floor()).clamp(0.0, 82.0) as u32;
+    let ac_scale = (quantised_max as f32 + 1.0) / 166.0;
+
+    let mut hash = String::with_capacity(1 + 1 + 4 + 2 * ac.len());
+    let size_flag = (components_x - 1) + (components_y - 1) * 9;
+    encode_base83_into(size_flag, 1, &mut hash);
+    encode_base83_into(quantised_max, 1, &mut hash);
+    encode_base83_into(encode_dc(dc), 4, &mut hash);
+    for component in ac {
+        encode_base83_into(encode_ac(*component, ac_scale), 2, &mut hash);
+    }
+
+    Ok(hash)
+}
+
+fn basis_factor(
+    linear: &[f32],
+    width: usize,
+    height: usize,
+    i: u32,
+    j: u32,
+    normalisation: f32,
+) -> [f32; 3] {
+    let mut sum = [0.0f32; 3];
+    for py in 0..height {
+        let basis_y = (std::f32::consts::PI * j as f32 * py as f32 / height as f32).cos();
+        for px in 0..width {
+            let basis = basis_y
+                * (std::f32::consts::PI * i as f32 * px as f32 / width as f32).cos();
+            let idx = (py * width + px) * 3;
+            sum[0] += basis * linear[idx];
+            sum[1] += basis * linear[idx + 1];
+            sum[2] += basis * linear[idx + 2];
+        }
+    }
+
+    let scale = normalisation / (width as f32 * height as f32);
+    [sum[0] * scale, sum[1] * scale, sum[2] * scale]
+}
+
+fn encode_dc(value: [f32; 3]) -> u32 {
+    let r = linear_to_srgb(value[0]);
+    let g = linear_to_srgb(value[1]);
+    let b = linear_to_srgb(value[2]);
+    r * 65536 + g * 256 + b
+}
+
+fn encode_ac(value: [f32; 3], scale: f32) -> u32 {
+    let r = quantise_ac(value[0], scale);
+    let g = quantise_ac(value[1], scale);
+    let b = quantise_ac(value[2], scale);
+    r * 19 * 19 + g * 19 + b
+}
+
+fn quantise_ac(value: f32, scale: f32) -> u32 {
+    let normalised = value / scale;
+    let magnitude = (normalised.abs().powf(0.5) * 9.0 + 0.5).floor();
+    let signed = normalised.signum() * magnitude;
+    (signed.clamp(-9.0, 9.0) as i32 + 9) as u32
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let c = value as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u32 {
+    let v = value.clamp(0.0, 1.0);
+    let s = if v <= 0.0031308 {
+        v * 12.92 * 255.0 + 0.5
+    } else {
+        (1.055 * v.powf(1.0 / 2.4) - 0.055) * 255.0 + 0.5
+    };
+    s.floor().clamp(0.0, 255.0) as u32
+}
+
+fn encode_base83_into(value: u32, length: usize, out: &mut String) {
+    for i in 1..=length {
+        let digit = (value / 83u32.pow((length - i) as u32)) % 83;
+        out.push(BLURHASH_ALPHABET[digit as usize] as char);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use image::ImageFormat;
 
-    use super::{EncodedImageFormat, FACE_THUMBNAIL_JPEG_QUALITY, encode_rgb};
+    use super::{
+        EncodedImageFormat, FACE_THUMBNAIL_JPEG_QUALITY, compute_blurhash, encode_rgb,
+    };
+    use crate::ml::types::{DecodedImage, Dimensions};
+
+    fn solid_image(width: u32, height: u32, rgb: [u8; 3]) -> DecodedImage {
+        let mut buffer = Vec::with_capacity((width * height * 3) as usize);
+        for _ in 0..(width * height) {
+            buffer.extend_from_slice(&rgb);
+        }
+        DecodedImage {
+            dimensions: Dimensions { width, height },
+            rgb: buffer,
+            source_bit_depth: 8,
+        }
+    }
+
+    #[test]
+    fn compute_blurhash_has_expected_length_and_size_flag() {
+        let image = solid_image(8, 8, [200, 40, 40]);
+        let hash = compute_blurhash(&image, 4, 3).expect("blurhash should compute");
+
+        // 1 size char + 1 max-AC char + 4 DC chars + 2 per AC component.
+        assert_eq!(hash.len(), 1 + 1 + 4 + 2 * (4 * 3 - 1));
+        // Size flag = (x-1) + (y-1)*9 = 3 + 18 = 21 -> 'L' in the base83 alphabet.
+        assert_eq!(hash.as_bytes()[0], super::BLURHASH_ALPHABET[21]);
+    }
+
+    #[test]
+    fn compute_blurhash_rejects_out_of_range_components() {
+        let image = solid_image(4, 4, [0, 0, 0]);
+        assert!(compute_blurhash(&image, 0, 3).is_err());
+        assert!(compute_blurhash(&image, 4, 10).is_err());
+    }
+
+    #[test]
+    fn compute_blurhash_rejects_zero_dimension() {
+        let image = DecodedImage {
+            dimensions: Dimensions {
+                width: 0,
+                height: 4,
+            },
+            rgb: Vec::new(),
+            source_bit_depth: 8,
+        };
+        assert!(compute_blurhash(&image, 4, 4).is_err());
+    }
 
     #[test]
     fn encode_rgb_jpeg_produces_valid_jpeg() {
@@ -77,6 +376,60 @@ mod tests {
             .expect("encoded bytes should be valid JPEG");
     }
 
+    #[test]
+    fn encode_rgb_webp_produces_valid_webp() {
+        let rgb = vec![255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+        let encoded = encode_rgb(&rgb, 2, 2, EncodedImageFormat::WebP { quality: 90 })
+            .expect("webp encoding should succeed");
+
+        assert!(!encoded.is_empty());
+        image::load_from_memory_with_format(&encoded, ImageFormat::WebP)
+            .expect("encoded bytes should be valid WebP");
+    }
+
+    #[test]
+    fn encode_rgb_png_optimized_is_valid_and_no_larger() {
+        // A gradient row pattern gives the adaptive filter something to exploit.
+        let (width, height) = (16u32, 16u32);
+        let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                rgb.push((x * 8) as u8);
+                rgb.push((y * 8) as u8);
+                rgb.push(((x + y) * 4) as u8);
+            }
+        }
+
+        let baseline = encode_rgb(&rgb, width, height, EncodedImageFormat::Png { optimize: false })
+            .expect("png encoding should succeed");
+        let optimized = encode_rgb(&rgb, width, height, EncodedImageFormat::Png { optimize: true })
+            .expect("optimized png encoding should succeed");
+
+        image::load_from_memory_with_format(&optimized, ImageFormat::Png)
+            .expect("optimized bytes should be valid PNG");
+        assert!(optimized.len() <= baseline.len());
+    }
+
+    #[cfg(feature = "avif")]
+    #[test]
+    fn encode_rgb_avif_produces_valid_avif() {
+        let rgb = vec![255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+        let encoded = encode_rgb(
+            &rgb,
+            2,
+            2,
+            EncodedImageFormat::Avif {
+                quality: 80,
+                speed: 8,
+            },
+        )
+        .expect("avif encoding should succeed");
+
+        assert!(!encoded.is_empty());
+        image::load_from_memory_with_format(&encoded, ImageFormat::Avif)
+            .expect("encoded bytes should be valid AVIF");
+    }
+
     #[test]
     fn encode_rgb_rejects_invalid_buffer_size() {
         let invalid_rgb = vec![0, 1, 2];
@@ -0,0 +1,307 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use crate::ml::error::{MlError, MlResult};
+
+/// Content hash of a model input tensor. Two aligned faces that produce byte-identical input
+/// tensors share a key, so re-indexing unchanged content hits the cache instead of the model.
+pub type TensorHash = [u8; 32];
+
+const CACHE_MAGIC: &[u8; 4] = b"EMBC";
+const CACHE_VERSION: u32 = 1;
+
+/// Advance `cursor` by `len` bytes over `bytes`, returning the consumed slice or a truncation
+/// error. Used by the cache deserializer to read fixed-width fields without panicking on a
+/// short file.
+fn take<'a>(bytes: &'a [u8], cursor: &mut usize, len: usize) -> MlResult<&'a [u8]> {
+    let end = cursor
+        .checked_add(len)
+        .filter(|end| *end <= bytes.len())
+        .ok_or_else(|| MlError::Runtime("truncated embedding cache".to_string()))?;
+    let slice = &bytes[*cursor..end];
+    *cursor = end;
+    Ok(slice)
+}
+
+/// Configuration for the content-addressed embedding cache, carried on
+/// [`crate::ml::runtime::MlRuntimeConfig`]. A `max_entries` of zero disables caching.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmbeddingCacheConfig {
+    /// Upper bound on retained embeddings; the oldest entries are evicted first.
+    pub max_entries: usize,
+    /// Optional on-disk location. When set the cache is loaded on start and flushed after new
+    /// embeddings are computed, so an incremental re-index survives app restarts.
+    pub path: Option<String>,
+}
+
+impl Default for EmbeddingCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 4096,
+            path: None,
+        }
+    }
+}
+
+/// A bounded, optionally persisted map from input-tensor hash to its computed embedding.
+///
+/// Insertion order is tracked so the cache can evict FIFO once it reaches `max_entries`,
+/// which keeps peak memory predictable on-device. All stored embeddings share a single
+/// dimensionality (the first insert fixes it); an embedding of a different size is rejected
+/// rather than silently corrupting the on-disk layout.
+#[derive(Debug)]
+pub struct EmbeddingCache {
+    config: EmbeddingCacheConfig,
+    entries: HashMap<TensorHash, Vec<f32>>,
+    order: VecDeque<TensorHash>,
+    dimension: Option<usize>,
+    dirty: bool,
+}
+
+impl EmbeddingCache {
+    /// Build a cache for `config`, loading any existing on-disk contents. A corrupt or
+    /// unreadable cache file is treated as empty rather than failing runtime construction.
+    pub fn new(config: &EmbeddingCacheConfig) -> Self {
+        let mut cache = Self {
+            config: config.clone(),
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            dimension: None,
+            dirty: false,
+        };
+        if cache.is_enabled() {
+            if let Some(path) = cache.config.path.as_deref() {
+                if let Err(error) = cache.load(Path::new(path)) {
+                    // A stale or partial cache is recoverable: start fresh and let it repopulate.
+                    eprintln!("Discarding unreadable embedding cache {path}: {error}");
+                    cache.entries.clear();
+                    cache.order.clear();
+                    cache.dimension = None;
+                }
+            }
+        }
+        cache
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.config.max_entries > 0
+    }
+
+    /// Hash the raw bytes of a model input tensor into a cache key.
+    pub fn hash_tensor(tensor: &[f32]) -> TensorHash {
+        let mut hasher = blake3::Hasher::new();
+        for value in tensor {
+            hasher.update(&value.to_le_bytes());
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Return a previously computed embedding for `key`, if present.
+    pub fn get(&self, key: &TensorHash) -> Option<Vec<f32>> {
+        if !self.is_enabled() {
+            return None;
+        }
+        self.entries.get(key).cloned()
+    }
+
+    /// Insert `embedding` under `key`, evicting the oldest entry if the cache is full. A
+    /// re-inserted key refreshes its value without changing its eviction position, and an
+    /// embedding whose length disagrees with earlier entries is ignored.
+    pub fn insert(&mut self, key: TensorHash, embedding: Vec<f32>) {
+        if !self.is_enabled() {
+            return;
+        }
+        match self.dimension {
+            Some(dimension) if dimension != embedding.len() => return,
+            None => self.dimension = Some(embedding.len()),
+            _ => {}
+        }
+
+        if self.entries.insert(key, embedding).is_none() {
+            self.order.push_back(key);
+            while self.order.len() > self.config.max_entries {
+                if let Some(evicted) = self.order.pop_front() {
+                    self.entries.remove(&evicted);
+                }
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Persist the cache to its configured path if it has unsaved changes. A no-op when the
+    /// cache is disabled or has no path.
+    pub fn flush(&mut self) -> MlResult<()> {
+        if !self.is_enabled() || !self.dirty {
+            return Ok(());
+        }
+        let Some(path) = self.config.path.clone() else {
+            self.dirty = false;
+            return Ok(());
+        };
+        self.save(Path::new(&path))?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn save(&self, path: &Path) -> MlResult<()> {
+        let dimension = self.dimension.unwrap_or(0);
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(CACHE_MAGIC);
+        buffer.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        buffer.extend_from_slice(&(dimension as u32).to_le_bytes());
+        buffer.extend_from_slice(&(self.order.len() as u32).to_le_bytes());
+        // Persist in eviction order so a reload preserves the FIFO position of each entry.
+        for key in &self.order {
+            let Some(embedding) = self.entries.get(key) else {
+                continue;
+            };
+            buffer.extend_from_slice(key);
+            for value in embedding {
+                buffer.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        let path = PathBuf::from(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                MlError::Runtime(format!(
+                    "failed to create embedding cache directory {}: {e}",
+                    parent.display()
+                ))
+            })?;
+        }
+        // Write-then-rename so a crash mid-write can't leave a truncated cache behind.
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, &buffer).map_err(|e| {
+            MlError::Runtime(format!(
+                "failed to write embedding cache {}: {e}",
+                temp_path.display()
+            ))
+        })?;
+        std::fs::rename(&temp_path, &path).map_err(|e| {
+            let _ = std::fs::remove_file(&temp_path);
+            MlError::Runtime(format!(
+                "failed to persist embedding cache {}: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    fn load(&mut self, path: &Path) -> MlResult<()> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => {
+                return Err(MlError::Runtime(format!(
+                    "failed to read embedding cache {}: {error}",
+                    path.display()
+                )));
+            }
+        };
+
+        let mut cursor = 0usize;
+        if take(&bytes, &mut cursor, 4)? != CACHE_MAGIC {
+            return Err(MlError::Runtime("bad embedding cache magic".to_string()));
+        }
+        let version = u32::from_le_bytes(take(&bytes, &mut cursor, 4)?.try_into().unwrap());
+        if version != CACHE_VERSION {
+            return Err(MlError::Runtime(format!(
+                "unsupported embedding cache version {version}"
+            )));
+        }
+        let dimension = u32::from_le_bytes(take(&bytes, &mut cursor, 4)?.try_into().unwrap()) as usize;
+        let count = u32::from_le_bytes(take(&bytes, &mut cursor, 4)?.try_into().unwrap()) as usize;
+        if dimension == 0 {
+            return Ok(());
+        }
+
+        for _ in 0..count {
+            let mut key = [0u8; 32];
+            key.copy_from_slice(take(&bytes, &mut cursor, 32)?);
+            let raw = take(&bytes, &mut cursor, dimension * 4)?;
+            let embedding = raw
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+                .collect::<Vec<f32>>();
+            self.dimension = Some(dimension);
+            if self.entries.insert(key, embedding).is_none() {
+                self.order.push_back(key);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static TEST_PATH_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    fn temp_cache_path() -> PathBuf {
+        let sequence = TEST_PATH_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!(
+            "embedding_cache_test_{}_{}.bin",
+            std::process::id(),
+            sequence
+        ))
+    }
+
+    #[test]
+    fn identical_tensors_hash_equal() {
+        let a = vec![0.1, 0.2, 0.3];
+        let b = vec![0.1, 0.2, 0.3];
+        let c = vec![0.1, 0.2, 0.4];
+        assert_eq!(EmbeddingCache::hash_tensor(&a), EmbeddingCache::hash_tensor(&b));
+        assert_ne!(EmbeddingCache::hash_tensor(&a), EmbeddingCache::hash_tensor(&c));
+    }
+
+    #[test]
+    fn insert_evicts_oldest_once_full() {
+        let mut cache = EmbeddingCache::new(&EmbeddingCacheConfig {
+            max_entries: 2,
+            path: None,
+        });
+        let keys: Vec<TensorHash> = (0u8..3).map(|i| [i; 32]).collect();
+        for (index, key) in keys.iter().enumerate() {
+            cache.insert(*key, vec![index as f32]);
+        }
+        // The first insert should have been evicted, the last two retained.
+        assert!(cache.get(&keys[0]).is_none());
+        assert_eq!(cache.get(&keys[1]), Some(vec![1.0]));
+        assert_eq!(cache.get(&keys[2]), Some(vec![2.0]));
+    }
+
+    #[test]
+    fn disabled_cache_never_stores() {
+        let mut cache = EmbeddingCache::new(&EmbeddingCacheConfig {
+            max_entries: 0,
+            path: None,
+        });
+        cache.insert([7; 32], vec![1.0, 2.0]);
+        assert!(cache.get(&[7; 32]).is_none());
+    }
+
+    #[test]
+    fn survives_a_flush_and_reload() {
+        let path = temp_cache_path();
+        let path_str = path.to_str().unwrap().to_string();
+        {
+            let mut cache = EmbeddingCache::new(&EmbeddingCacheConfig {
+                max_entries: 8,
+                path: Some(path_str.clone()),
+            });
+            cache.insert([1; 32], vec![0.5, 0.25]);
+            cache.insert([2; 32], vec![0.125, 0.0625]);
+            cache.flush().expect("flush should succeed");
+        }
+        let reloaded = EmbeddingCache::new(&EmbeddingCacheConfig {
+            max_entries: 8,
+            path: Some(path_str),
+        });
+        assert_eq!(reloaded.get(&[1; 32]), Some(vec![0.5, 0.25]));
+        assert_eq!(reloaded.get(&[2; 32]), Some(vec![0.125, 0.0625]));
+        let _ = std::fs::remove_file(path);
+    }
+}
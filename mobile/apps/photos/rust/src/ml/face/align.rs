@@ -2,12 +2,20 @@ use image::{ImageBuffer, Rgb, RgbImage};
 use imageproc::geometric_transformations::{Interpolation, Projection, warp_into};
 use nalgebra::{Matrix2, Matrix3, Vector2};
 
+use crate::image::image_compression::compute_blurhash;
 use crate::ml::{
     error::{MlError, MlResult},
-    types::{AlignmentResult, DecodedImage, FaceDetection, FaceResult, to_face_id},
+    face::pose::estimate_head_pose,
+    normalize::affine_normalize,
+    types::{AlignmentResult, DecodedImage, Dimensions, FaceDetection, FaceResult, to_face_id},
 };
 
+/// Minimum absolute yaw (degrees) before a head is classified as turned left/right.
+const FACE_YAW_DIRECTION_THRESHOLD_DEGREES: f32 = 20.0;
+
 const FACE_SIZE: u32 = 112;
+const FACE_BLURHASH_COMPONENTS_X: u32 = 4;
+const FACE_BLURHASH_COMPONENTS_Y: u32 = 4;
 const LAPLACIAN_HARD_THRESHOLD: f32 = 10.0;
 const REMOVE_SIDE_COLUMNS: usize = 56;
 
@@ -46,11 +54,23 @@ pub fn run_face_alignment(
             decoded.dimensions.width,
             decoded.dimensions.height,
         );
-        let alignment = estimate_similarity_transform(&absolute_detection.keypoints)?;
+        let mut alignment = estimate_similarity_transform(&absolute_detection.keypoints)?;
+        // Prefer the continuous POS head-pose for direction; fall back to the coarse
+        // landmark heuristic when the solve is ill-conditioned.
+        let direction = match estimate_head_pose(&absolute_detection.keypoints) {
+            Some(pose) => {
+                alignment.yaw = pose.yaw;
+                alignment.pitch = pose.pitch;
+                alignment.roll = pose.roll;
+                direction_from_yaw(pose.yaw)
+            }
+            None => face_direction(&absolute_detection),
+        };
         let aligned = warp_face_image(&source, &alignment.affine_matrix)?;
         let normalized = normalize_face_rgb_for_mobilefacenet(&aligned);
-        let blur_value = compute_blur_value(&aligned, face_direction(&absolute_detection));
+        let blur_value = compute_blur_value(&aligned, direction);
         let face_id = to_face_id(file_id, detection.box_xyxy);
+        let blurhash = blurhash_for_aligned_face(&aligned);
 
         aligned_face_inputs.push(normalized);
         face_results.push(FaceResult {
@@ -59,6 +79,7 @@ pub fn run_face_alignment(
             alignment,
             embedding: Vec::new(),
             face_id,
+            blurhash,
         });
     }
 
@@ -89,17 +110,124 @@ fn to_absolute_detection(
     FaceDetectionAbsolute { keypoints }
 }
 
+/// Inlier reprojection threshold in normalized (112-pixel) landmark space: 4px at 112.
+const ALIGNMENT_INLIER_THRESHOLD: f32 = 4.0 / 112.0;
+
+/// Deterministic minimal subsets (3 of the 5 landmarks) sampled by the RANSAC loop. The
+/// fixed, exhaustive list keeps results reproducible without a random seed.
+const ALIGNMENT_RANSAC_SUBSETS: [[usize; 3]; 10] = [
+    [0, 1, 2],
+    [0, 1, 3],
+    [0, 1, 4],
+    [0, 2, 3],
+    [0, 2, 4],
+    [0, 3, 4],
+    [1, 2, 3],
+    [1, 2, 4],
+    [1, 3, 4],
+    [2, 3, 4],
+];
+
+/// Robustly estimate the similarity transform that maps the five detected landmarks onto
+/// the canonical MobileFaceNet landmarks. A single badly-placed keypoint (common on
+/// profile or occluded faces) skews a plain all-points least-squares fit, so we fit on
+/// minimal 3-point subsets, keep the model with the most inliers, and refit once on that
+/// inlier set. Falls back to the all-points fit when fewer than 3 inliers are found.
 fn estimate_similarity_transform(src_points: &[[f32; 2]; 5]) -> MlResult<AlignmentResult> {
+    let ideal = MOBILEFACENET_IDEAL_5_LANDMARKS;
+
+    let mut best: Option<(u32, AlignmentResult)> = None;
+    for subset in ALIGNMENT_RANSAC_SUBSETS {
+        let src_subset: Vec<[f32; 2]> = subset.iter().map(|&i| src_points[i]).collect();
+        let dst_subset: Vec<[f32; 2]> = subset.iter().map(|&i| ideal[i]).collect();
+        let Ok(model) = fit_similarity_transform(&src_subset, &dst_subset) else {
+            continue;
+        };
+        let inliers = count_inliers(&model, src_points, &ideal);
+        if best.as_ref().map_or(true, |(count, _)| inliers > *count) {
+            best = Some((inliers, model));
+        }
+    }
+
+    if let Some((count, model)) = &best {
+        if *count >= 3 {
+            let (src_inliers, dst_inliers) = inlier_pairs(model, src_points, &ideal);
+            if let Ok(mut refined) = fit_similarity_transform(&src_inliers, &dst_inliers) {
+                refined.inlier_count = *count;
+                return Ok(refined);
+            }
+        }
+    }
+
+    // Degenerate or too few inliers: fall back to the original all-points fit.
+    let mut model = fit_similarity_transform(&src_points[..], &ideal[..])?;
+    model.inlier_count = count_inliers(&model, src_points, &ideal);
+    Ok(model)
+}
+
+/// Project `point` through an alignment's 3x3 affine matrix into normalized landmark space.
+fn project_landmark(affine: &[[f32; 3]; 3], point: &[f32; 2]) -> [f32; 2] {
+    [
+        affine[0][0] * point[0] + affine[0][1] * point[1] + affine[0][2],
+        affine[1][0] * point[0] + affine[1][1] * point[1] + affine[1][2],
+    ]
+}
+
+fn count_inliers(
+    model: &AlignmentResult,
+    src_points: &[[f32; 2]; 5],
+    ideal: &[[f32; 2]; 5],
+) -> u32 {
+    src_points
+        .iter()
+        .zip(ideal.iter())
+        .filter(|(src, dst)| {
+            let projected = project_landmark(&model.affine_matrix, src);
+            let dx = projected[0] - dst[0];
+            let dy = projected[1] - dst[1];
+            (dx * dx + dy * dy).sqrt() <= ALIGNMENT_INLIER_THRESHOLD
+        })
+        .count() as u32
+}
+
+fn inlier_pairs(
+    model: &AlignmentResult,
+    src_points: &[[f32; 2]; 5],
+    ideal: &[[f32; 2]; 5],
+) -> (Vec<[f32; 2]>, Vec<[f32; 2]>) {
+    let mut src_inliers = Vec::new();
+    let mut dst_inliers = Vec::new();
+    for (src, dst) in src_points.iter().zip(ideal.iter()) {
+        let projected = project_landmark(&model.affine_matrix, src);
+        let dx = projected[0] - dst[0];
+        let dy = projected[1] - dst[1];
+        if (dx * dx + dy * dy).sqrt() <= ALIGNMENT_INLIER_THRESHOLD {
+            src_inliers.push(*src);
+            dst_inliers.push(*dst);
+        }
+    }
+    (src_inliers, dst_inliers)
+}
+
+/// Umeyama least-squares similarity fit over matched `src`/`dst` point sets. The caller
+/// sets `inlier_count`; this routine leaves it at zero.
+fn fit_similarity_transform(
+    src_points: &[[f32; 2]],
+    dst_points: &[[f32; 2]],
+) -> MlResult<AlignmentResult> {
+    if src_points.len() < 2 || src_points.len() != dst_points.len() {
+        return Err(MlError::Postprocess(
+            "similarity transform requires matched point sets of length >= 2".to_string(),
+        ));
+    }
+
     let src_mean = mean_2d(src_points);
-    let dst_mean = mean_2d(&MOBILEFACENET_IDEAL_5_LANDMARKS);
+    let dst_mean = mean_2d(dst_points);
     let n = src_points.len() as f32;
 
     let mut a = Matrix2::<f32>::zeros();
     let mut src_var_sum = 0.0f32;
-    for (src, dst) in src_points
-        .iter()
-        .zip(MOBILEFACENET_IDEAL_5_LANDMARKS.iter())
-    {
+    for (src, dst) in src_points.iter().zip(dst_points.iter()) {
         let src_d = Vector2::new(src[0] - src_mean.x, src[1] - src_mean.y);
         let dst_d = Vector2::new(dst[0] - dst_mean.x, dst[1] - dst_mean.y);
         a += dst_d * src_d.transpose();
@@ -181,6 +309,10 @@ fn estimate_similarity_transform(src_points: &[[f32; 2]; 5]) -> MlResult<Alignme
         center: [center[0], center[1]],
         size,
         rotation,
+        inlier_count: 0,
+        yaw: 0.0,
+        pitch: 0.0,
+        roll: 0.0,
     })
 }
 
@@ -231,18 +363,43 @@ fn warp_face_image(source: &RgbImage, affine_matrix: &[[f32; 3]; 3]) -> MlResult
 }
 
 fn normalize_face_rgb_for_mobilefacenet(face_image: &RgbImage) -> Vec<f32> {
-    let mut output = Vec::with_capacity((FACE_SIZE * FACE_SIZE * 3) as usize);
-    for y in 0..FACE_SIZE {
-        for x in 0..FACE_SIZE {
-            let px = face_image.get_pixel(x, y).0;
-            output.push(px[0] as f32 / 127.5 - 1.0);
-            output.push(px[1] as f32 / 127.5 - 1.0);
-            output.push(px[2] as f32 / 127.5 - 1.0);
-        }
-    }
+    // The RGB buffer is already contiguous in the layout MobileFaceNet expects, so the
+    // SIMD affine pass (`px / 127.5 - 1.0`) runs over it directly instead of per pixel.
+    let source = face_image.as_raw();
+    let mut output = vec![0.0f32; source.len()];
+    affine_normalize(source, 1.0 / 127.5, -1.0, &mut output);
     output
 }
 
+fn blurhash_for_aligned_face(face_image: &RgbImage) -> Option<String> {
+    let decoded = DecodedImage {
+        dimensions: Dimensions {
+            width: face_image.width(),
+            height: face_image.height(),
+        },
+        rgb: face_image.as_raw().clone(),
+        source_bit_depth: 8,
+    };
+    compute_blurhash(
+        &decoded,
+        FACE_BLURHASH_COMPONENTS_X,
+        FACE_BLURHASH_COMPONENTS_Y,
+    )
+    .ok()
+}
+
+/// Map a continuous yaw angle (degrees) onto the coarse [`FaceDirection`] buckets. A
+/// positive yaw turns the face toward the image's right.
+fn direction_from_yaw(yaw: f32) -> FaceDirection {
+    if yaw > FACE_YAW_DIRECTION_THRESHOLD_DEGREES {
+        FaceDirection::Right
+    } else if yaw < -FACE_YAW_DIRECTION_THRESHOLD_DEGREES {
+        FaceDirection::Left
+    } else {
+        FaceDirection::Straight
+    }
+}
+
 fn face_direction(detection: &FaceDetectionAbsolute) -> FaceDirection {
     let left_eye = detection.keypoints[0];
     let right_eye = detection.keypoints[1];
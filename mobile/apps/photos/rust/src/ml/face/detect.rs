@@ -10,9 +10,46 @@ const INPUT_HEIGHT: f32 = 640.0;
 const IOU_THRESHOLD: f32 = 0.4;
 const MIN_SCORE_THRESHOLD: f32 = 0.5;
 
+/// How overlapping detection boxes are collapsed after the detector runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NmsMode {
+    /// Hard-threshold NMS: discard any box whose IoU with a higher-scoring kept box exceeds
+    /// the threshold. Simple, but drops genuinely overlapping faces in dense group shots.
+    Hard,
+    /// Soft-NMS with a Gaussian penalty: an overlapping box keeps its identity but has its
+    /// score decayed by `exp(-iou² / sigma)`, so overlapping-but-distinct faces survive
+    /// while exact duplicates decay below the score threshold and drop out.
+    SoftGaussian,
+}
+
+/// Tunables for the post-detection suppression pass. The defaults run Soft-NMS with the
+/// detector's original IoU and score thresholds, which keeps crowded group photos intact.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NmsConfig {
+    pub mode: NmsMode,
+    /// IoU above which `Hard` mode removes a box outright (unused by `SoftGaussian`).
+    pub iou_threshold: f32,
+    /// Gaussian width for `SoftGaussian`; larger values decay overlapping boxes less.
+    pub sigma: f32,
+    /// Boxes whose (possibly decayed) score falls below this are pruned.
+    pub score_threshold: f32,
+}
+
+impl Default for NmsConfig {
+    fn default() -> Self {
+        Self {
+            mode: NmsMode::SoftGaussian,
+            iou_threshold: IOU_THRESHOLD,
+            sigma: 0.5,
+            score_threshold: MIN_SCORE_THRESHOLD,
+        }
+    }
+}
+
 pub fn run_face_detection(
     runtime: &mut MlRuntime,
     decoded: &DecodedImage,
+    nms: &NmsConfig,
 ) -> MlResult<Vec<FaceDetection>> {
     let (input, scaled_width, scaled_height) = preprocess::preprocess_yolo(decoded)?;
     let face_detection = runtime.face_detection_session_mut()?;
@@ -73,7 +110,7 @@ pub fn run_face_detection(
     }
 
     let _ = output_shape;
-    Ok(naive_non_max_suppression(detections, IOU_THRESHOLD))
+    Ok(non_max_suppression(detections, nms))
 }
 
 fn correct_for_maintained_aspect_ratio(
@@ -100,26 +137,61 @@ fn correct_for_maintained_aspect_ratio(
     }
 }
 
-fn naive_non_max_suppression(
-    mut detections: Vec<FaceDetection>,
-    iou_threshold: f32,
-) -> Vec<FaceDetection> {
-    detections.sort_by(|a, b| b.score.total_cmp(&a.score));
-
-    let mut i = 0usize;
-    while i + 1 < detections.len() {
-        let mut j = i + 1;
-        while j < detections.len() {
-            let iou = calculate_iou(&detections[i], &detections[j]);
-            if iou >= iou_threshold {
-                detections.remove(j);
-            } else {
-                j += 1;
+/// Suppress overlapping detections.
+///
+/// Works over a mutable `(detection, current score)` pool instead of repeated
+/// `Vec::remove`: each round the highest-scoring box is popped and kept, then every
+/// remaining box is either removed ([`NmsMode::Hard`]) or has its score decayed by the
+/// Gaussian factor `exp(-iou² / sigma)` ([`NmsMode::SoftGaussian`]) and pruned once it
+/// drops below `score_threshold`. Because the pool only ever loses boxes or lowers scores,
+/// the kept boxes come out in descending final-score order. The reported `score` of each
+/// kept detection is its decayed score at the moment it was selected.
+fn non_max_suppression(detections: Vec<FaceDetection>, config: &NmsConfig) -> Vec<FaceDetection> {
+    let mut pool: Vec<(FaceDetection, f32)> = detections
+        .into_iter()
+        .map(|detection| {
+            let score = detection.score;
+            (detection, score)
+        })
+        .collect();
+    let mut kept: Vec<FaceDetection> = Vec::with_capacity(pool.len());
+
+    while !pool.is_empty() {
+        let best_index = pool
+            .iter()
+            .enumerate()
+            .max_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))
+            .map(|(index, _)| index)
+            .expect("pool is non-empty");
+        let (mut best, best_score) = pool.swap_remove(best_index);
+        // Everything left scores no higher than this box, so once it falls below the
+        // threshold nothing remaining can clear it either.
+        if best_score < config.score_threshold {
+            break;
+        }
+        best.score = best_score;
+
+        let mut survivors = Vec::with_capacity(pool.len());
+        for (detection, score) in pool.drain(..) {
+            let iou = calculate_iou(&best, &detection);
+            let decayed = match config.mode {
+                NmsMode::Hard => {
+                    if iou >= config.iou_threshold {
+                        continue;
+                    }
+                    score
+                }
+                NmsMode::SoftGaussian => score * (-(iou * iou) / config.sigma).exp(),
+            };
+            if decayed >= config.score_threshold {
+                survivors.push((detection, decayed));
             }
         }
-        i += 1;
+        pool = survivors;
+        kept.push(best);
     }
-    detections
+
+    kept
 }
 
 fn calculate_iou(a: &FaceDetection, b: &FaceDetection) -> f32 {
@@ -146,3 +218,72 @@ fn calculate_iou(a: &FaceDetection, b: &FaceDetection) -> f32 {
     }
     intersection_area / union_area
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detection(score: f32, x_min: f32, y_min: f32, x_max: f32, y_max: f32) -> FaceDetection {
+        FaceDetection {
+            score,
+            box_xyxy: [x_min, y_min, x_max, y_max],
+            keypoints: [[0.0, 0.0]; 5],
+        }
+    }
+
+    #[test]
+    fn hard_mode_drops_overlapping_boxes() {
+        let config = NmsConfig {
+            mode: NmsMode::Hard,
+            ..NmsConfig::default()
+        };
+        let detections = vec![
+            detection(0.9, 0.0, 0.0, 0.5, 0.5),
+            detection(0.8, 0.01, 0.01, 0.51, 0.51),
+        ];
+        let kept = non_max_suppression(detections, &config);
+        assert_eq!(kept.len(), 1);
+        assert!((kept[0].score - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn soft_mode_keeps_overlapping_but_distinct_faces() {
+        let config = NmsConfig::default();
+        // Two heavily overlapping boxes, both confidently scored: Soft-NMS decays the second
+        // but keeps it, where Hard mode would have removed it.
+        let detections = vec![
+            detection(0.95, 0.0, 0.0, 0.5, 0.5),
+            detection(0.9, 0.1, 0.1, 0.6, 0.6),
+        ];
+        let kept = non_max_suppression(detections, &config);
+        assert_eq!(kept.len(), 2);
+        assert!(kept[1].score < 0.9);
+    }
+
+    #[test]
+    fn results_are_sorted_by_descending_final_score() {
+        let config = NmsConfig::default();
+        let detections = vec![
+            detection(0.6, 0.0, 0.0, 0.2, 0.2),
+            detection(0.95, 0.5, 0.5, 0.7, 0.7),
+            detection(0.8, 0.8, 0.8, 0.95, 0.95),
+        ];
+        let kept = non_max_suppression(detections, &config);
+        assert_eq!(kept.len(), 3);
+        for pair in kept.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+        }
+    }
+
+    #[test]
+    fn exact_duplicates_decay_below_the_threshold() {
+        let config = NmsConfig::default();
+        // A barely-above-threshold duplicate of a strong box decays out entirely.
+        let detections = vec![
+            detection(0.95, 0.0, 0.0, 0.5, 0.5),
+            detection(0.52, 0.0, 0.0, 0.5, 0.5),
+        ];
+        let kept = non_max_suppression(detections, &config);
+        assert_eq!(kept.len(), 1);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::ml::{
+    embedding_cache::{EmbeddingCache, TensorHash},
     error::{MlError, MlResult},
     onnx,
     runtime::MlRuntime,
@@ -9,6 +10,27 @@ const FACE_INPUT_WIDTH: i64 = 112;
 const FACE_INPUT_HEIGHT: i64 = 112;
 const FACE_INPUT_CHANNELS: i64 = 3;
 
+/// Batching budget for [`EmbeddingQueue`]. A batch is flushed as soon as either limit would be
+/// exceeded by the next face, so peak allocation stays bounded regardless of how many faces a
+/// single image contains.
+#[derive(Clone, Debug)]
+pub struct EmbeddingQueueConfig {
+    /// Maximum number of faces assembled into one `session.run`.
+    pub max_batch_count: usize,
+    /// Maximum total f32 values held in a pending batch's input tensor.
+    pub max_batch_floats: usize,
+}
+
+impl Default for EmbeddingQueueConfig {
+    fn default() -> Self {
+        // ~16 aligned 112×112×3 faces, i.e. about 2.4 MB of f32 input at a time.
+        Self {
+            max_batch_count: 16,
+            max_batch_floats: 16 * (FACE_INPUT_WIDTH * FACE_INPUT_HEIGHT * FACE_INPUT_CHANNELS) as usize,
+        }
+    }
+}
+
 pub fn run_face_embedding(
     runtime: &mut MlRuntime,
     aligned_faces: &[Vec<f32>],
@@ -26,8 +48,9 @@ pub fn run_face_embedding(
     }
 
     let per_face_len = (FACE_INPUT_WIDTH * FACE_INPUT_HEIGHT * FACE_INPUT_CHANNELS) as usize;
-    let mut input = Vec::with_capacity(per_face_len * aligned_faces.len());
-    for aligned in aligned_faces {
+    let mut queue = EmbeddingQueue::new(EmbeddingQueueConfig::default(), per_face_len);
+
+    for (index, aligned) in aligned_faces.iter().enumerate() {
         if aligned.len() != per_face_len {
             return Err(MlError::Preprocess(format!(
                 "aligned face tensor length {} does not match expected {}",
@@ -35,48 +58,124 @@ pub fn run_face_embedding(
                 per_face_len
             )));
         }
-        input.extend_from_slice(aligned);
-    }
 
-    let (shape, output) = onnx::run_f32(
-        &mut runtime.face_embedding,
-        input,
-        vec![
-            aligned_faces.len() as i64,
-            FACE_INPUT_HEIGHT,
-            FACE_INPUT_WIDTH,
-            FACE_INPUT_CHANNELS,
-        ],
-    )?;
-    if shape.is_empty() {
-        return Err(MlError::Postprocess(
-            "face embedding output shape is empty".to_string(),
-        ));
+        // Reuse a cached embedding when the aligned input is byte-identical to one we've seen,
+        // so only genuinely new faces are enqueued for inference.
+        let hash = EmbeddingCache::hash_tensor(aligned);
+        if let Some(embedding) = runtime.cached_embedding(&hash) {
+            face_results[index].embedding = embedding;
+            continue;
+        }
+
+        queue.push(runtime, face_results, index, hash, aligned)?;
     }
-    let batch = shape[0] as usize;
-    if batch != face_results.len() {
-        return Err(MlError::Postprocess(format!(
-            "face embedding batch mismatch: output={batch}, expected={}",
-            face_results.len()
-        )));
+
+    queue.flush(runtime, face_results)?;
+
+    if let Err(error) = runtime.flush_embedding_cache() {
+        eprintln!("Failed to persist embedding cache: {error}");
     }
-    let embedding_size = output.len() / batch;
-    if embedding_size == 0 || output.len() != batch * embedding_size {
-        return Err(MlError::Postprocess(format!(
-            "invalid face embedding tensor shape {:?} for data length {}",
-            shape,
-            output.len()
-        )));
+
+    Ok(())
+}
+
+/// Accumulates aligned faces and runs them through the embedding session in bounded batches.
+///
+/// Faces are appended with [`push`](Self::push); when the next face would exceed either the
+/// count or float budget the current batch is flushed first. [`flush`](Self::flush) runs the
+/// pending input through [`onnx::run_f32`], normalizes each embedding, writes it back into the
+/// originating [`FaceResult`] slot, and records it in the runtime cache.
+struct EmbeddingQueue {
+    config: EmbeddingQueueConfig,
+    per_face_len: usize,
+    indices: Vec<usize>,
+    hashes: Vec<TensorHash>,
+    input: Vec<f32>,
+}
+
+impl EmbeddingQueue {
+    fn new(config: EmbeddingQueueConfig, per_face_len: usize) -> Self {
+        Self {
+            config,
+            per_face_len,
+            indices: Vec::new(),
+            hashes: Vec::new(),
+            input: Vec::new(),
+        }
     }
 
-    for (face_index, face_result) in face_results.iter_mut().enumerate() {
-        let start = face_index * embedding_size;
-        let mut embedding = output[start..(start + embedding_size)].to_vec();
-        normalize_embedding(&mut embedding);
-        face_result.embedding = embedding;
+    fn push(
+        &mut self,
+        runtime: &mut MlRuntime,
+        face_results: &mut [FaceResult],
+        index: usize,
+        hash: TensorHash,
+        aligned: &[f32],
+    ) -> MlResult<()> {
+        let would_overflow = self.indices.len() + 1 > self.config.max_batch_count
+            || self.input.len() + self.per_face_len > self.config.max_batch_floats;
+        if !self.indices.is_empty() && would_overflow {
+            self.flush(runtime, face_results)?;
+        }
+
+        self.indices.push(index);
+        self.hashes.push(hash);
+        self.input.extend_from_slice(aligned);
+        Ok(())
     }
 
-    Ok(())
+    fn flush(
+        &mut self,
+        runtime: &mut MlRuntime,
+        face_results: &mut [FaceResult],
+    ) -> MlResult<()> {
+        if self.indices.is_empty() {
+            return Ok(());
+        }
+
+        let batch = self.indices.len();
+        let (shape, output) = onnx::run_f32(
+            runtime.face_embedding_session_mut()?,
+            std::mem::take(&mut self.input),
+            vec![
+                batch as i64,
+                FACE_INPUT_HEIGHT,
+                FACE_INPUT_WIDTH,
+                FACE_INPUT_CHANNELS,
+            ],
+        )?;
+        if shape.is_empty() {
+            return Err(MlError::Postprocess(
+                "face embedding output shape is empty".to_string(),
+            ));
+        }
+        let output_batch = shape[0] as usize;
+        if output_batch != batch {
+            return Err(MlError::Postprocess(format!(
+                "face embedding batch mismatch: output={output_batch}, expected={batch}"
+            )));
+        }
+        let embedding_size = output.len() / batch;
+        if embedding_size == 0 || output.len() != batch * embedding_size {
+            return Err(MlError::Postprocess(format!(
+                "invalid face embedding tensor shape {:?} for data length {}",
+                shape,
+                output.len()
+            )));
+        }
+
+        for (slot, (&index, &hash)) in self.indices.iter().zip(self.hashes.iter()).enumerate() {
+            let start = slot * embedding_size;
+            let mut embedding = output[start..(start + embedding_size)].to_vec();
+            normalize_embedding(&mut embedding);
+            runtime.cache_embedding(hash, embedding.clone());
+            face_results[index].embedding = embedding;
+        }
+
+        self.indices.clear();
+        self.hashes.clear();
+        Ok(())
+    }
 }
 
 fn normalize_embedding(embedding: &mut [f32]) {
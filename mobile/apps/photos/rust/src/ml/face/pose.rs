@@ -0,0 +1,126 @@
+use nalgebra::{Matrix3, Vector3};
+
+/// Continuous 3D head pose in degrees, recovered from the five detected landmarks.
+#[derive(Clone, Copy, Debug)]
+pub struct HeadPose {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+}
+
+/// Canonical 3D coordinates of the five landmarks (left eye, right eye, nose tip, left
+/// mouth corner, right mouth corner) in a face-centric frame: `x` points to the subject's
+/// right in image space, `y` points down, and `z` points out of the face toward the
+/// camera. The nose tip protrudes so the model is non-coplanar, which is what lets the
+/// POS solve recover depth.
+const MODEL_LANDMARKS: [[f32; 3]; 5] = [
+    [-0.30, -0.30, -0.10],
+    [0.30, -0.30, -0.10],
+    [0.00, 0.00, 0.00],
+    [-0.25, 0.30, -0.10],
+    [0.25, 0.30, -0.10],
+];
+
+const REFERENCE_INDEX: usize = 2;
+
+/// Estimate head pose from the five landmarks using a weak-perspective POS (Pose from
+/// Orthography and Scaling) solve: with a scaled-orthographic camera, each correspondence
+/// is `image_point ≈ s·[i;j]·model_point + t`. We recover the two rotation basis rows `i`
+/// and `j` by least squares, orthonormalize them, complete the basis with `k = i × j`, and
+/// decode Euler angles. Returns `None` when the recovered rows are degenerate (non-finite,
+/// near zero length, or parallel, as with collinear landmarks), so the caller can fall back
+/// to the coarse heuristic.
+pub fn estimate_head_pose(landmarks: &[[f32; 2]; 5]) -> Option<HeadPose> {
+    let reference_model = Vector3::from(MODEL_LANDMARKS[REFERENCE_INDEX]);
+    let reference_image = landmarks[REFERENCE_INDEX];
+
+    // Accumulate the normal equations A^T A, A^T x, A^T y for the 3 unknowns, avoiding a
+    // dynamically-sized pseudo-inverse.
+    let mut ata = Matrix3::<f32>::zeros();
+    let mut atx = Vector3::<f32>::zeros();
+    let mut aty = Vector3::<f32>::zeros();
+    for index in 0..5 {
+        if index == REFERENCE_INDEX {
+            continue;
+        }
+        let model_delta = Vector3::from(MODEL_LANDMARKS[index]) - reference_model;
+        ata += model_delta * model_delta.transpose();
+        atx += model_delta * (landmarks[index][0] - reference_image[0]);
+        aty += model_delta * (landmarks[index][1] - reference_image[1]);
+    }
+
+    let inverse = ata.try_inverse()?;
+    let i_vec = inverse * atx;
+    let j_vec = inverse * aty;
+
+    let i_norm = i_vec.norm();
+    let j_norm = j_vec.norm();
+    if !i_norm.is_finite() || !j_norm.is_finite() || i_norm < 1e-6 || j_norm < 1e-6 {
+        return None;
+    }
+
+    // Orthonormalize: keep i, project j off it, then complete with k = i × j.
+    let i_hat = i_vec / i_norm;
+    let j_proj = j_vec / j_norm;
+    let j_ortho = j_proj - i_hat * i_hat.dot(&j_proj);
+    let j_len = j_ortho.norm();
+    if j_len < 1e-6 {
+        return None;
+    }
+    let j_hat = j_ortho / j_len;
+    let k_hat = i_hat.cross(&j_hat);
+
+    let rotation = Matrix3::from_rows(&[
+        i_hat.transpose(),
+        j_hat.transpose(),
+        k_hat.transpose(),
+    ]);
+
+    // Tait–Bryan XYZ decomposition of the rotation matrix.
+    let sy = (rotation[(0, 0)].powi(2) + rotation[(1, 0)].powi(2)).sqrt();
+    let pitch = rotation[(2, 1)].atan2(rotation[(2, 2)]);
+    let yaw = (-rotation[(2, 0)]).atan2(sy);
+    let roll = rotation[(1, 0)].atan2(rotation[(0, 0)]);
+
+    Some(HeadPose {
+        yaw: yaw.to_degrees(),
+        pitch: pitch.to_degrees(),
+        roll: roll.to_degrees(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::estimate_head_pose;
+
+    #[test]
+    fn frontal_face_is_near_zero_yaw() {
+        // Landmarks matching the canonical model projected straight on: small angles.
+        let landmarks = [
+            [-0.30, -0.30],
+            [0.30, -0.30],
+            [0.00, 0.00],
+            [-0.25, 0.30],
+            [0.25, 0.30],
+        ];
+        let pose = estimate_head_pose(&landmarks).expect("frontal pose should solve");
+        assert!(pose.yaw.abs() < 5.0, "yaw was {}", pose.yaw);
+        assert!(pose.roll.abs() < 5.0, "roll was {}", pose.roll);
+    }
+
+    #[test]
+    fn horizontally_compressed_face_yaws() {
+        // Compressing the x extent (eyes/mouth pulled toward the nose on one side) is what
+        // a yawed head looks like under weak perspective; the solve should report a
+        // non-trivial yaw rather than zero.
+        let landmarks = [
+            [-0.05, -0.30],
+            [0.30, -0.30],
+            [0.00, 0.00],
+            [-0.04, 0.30],
+            [0.25, 0.30],
+        ];
+        let pose = estimate_head_pose(&landmarks).expect("pose should solve");
+        assert!(pose.yaw.abs() > 5.0, "expected a yaw, got {}", pose.yaw);
+    }
+}
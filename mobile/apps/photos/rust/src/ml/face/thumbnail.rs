@@ -4,16 +4,37 @@ use fast_image_resize::{
 };
 
 use crate::image::image_compression::{
-    EncodedImageFormat, FACE_THUMBNAIL_JPEG_QUALITY, FACE_THUMBNAIL_MIN_DIMENSION, encode_rgb,
+    EncodedImageFormat, FACE_THUMBNAIL_MIN_DIMENSION, encode_rgb, encode_rgb16,
 };
 use crate::ml::{
     error::{MlError, MlResult},
-    types::DecodedImage,
+    types::{DecodedImage, DecodedImage16},
 };
 
 const REGULAR_PADDING: f64 = 0.4;
 const MINIMUM_PADDING: f64 = 0.1;
 
+/// How a [`ThumbnailSpec`] maps the source image into its target box.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThumbnailMethod {
+    /// Fit the whole image inside the box preserving aspect ratio; one output
+    /// dimension may end up smaller than requested.
+    Scale,
+    /// Fill the box exactly, cropping the centered overflow on the longer axis.
+    Crop,
+}
+
+/// A single requested output size for [`generate_thumbnails`].
+#[derive(Clone, Copy, Debug)]
+pub struct ThumbnailSpec {
+    pub width: u32,
+    pub height: u32,
+    pub method: ThumbnailMethod,
+    /// Output format for this size, letting a single batch mix e.g. JPEG fallbacks
+    /// with AVIF for clients that support it.
+    pub format: EncodedImageFormat,
+}
+
 #[derive(Clone, Debug)]
 pub struct FaceBox {
     pub x: f32,
@@ -35,6 +56,7 @@ struct CropRect {
 pub fn generate_face_thumbnails(
     decoded: &DecodedImage,
     face_boxes: &[FaceBox],
+    format: EncodedImageFormat,
 ) -> MlResult<Vec<Vec<u8>>> {
     if face_boxes.is_empty() {
         return Ok(Vec::new());
@@ -52,27 +74,228 @@ pub fn generate_face_thumbnails(
     let mut results = Vec::with_capacity(face_boxes.len());
 
     for (index, face_box) in face_boxes.iter().enumerate() {
-        let crop = compute_crop_rect(face_box, image_width, image_height).map_err(|e| {
-            MlError::InvalidRequest(format!("invalid face box at index {index}: {e}",))
-        })?;
-        let (target_width, target_height) =
-            dimensions_with_min_side(crop.output_width, crop.output_height)?;
-        let resized =
-            resize_crop_with_fir(&source, &crop, target_width, target_height, &mut resizer)?;
-        let compressed = encode_rgb(
-            resized.buffer(),
-            target_width,
-            target_height,
-            EncodedImageFormat::Jpeg {
-                quality: FACE_THUMBNAIL_JPEG_QUALITY,
-            },
+        let compressed = render_face_thumbnail(
+            &source,
+            face_box,
+            image_width,
+            image_height,
+            format,
+            &mut resizer,
+        )
+        .map_err(|e| MlError::InvalidRequest(format!("invalid face box at index {index}: {e}")))?;
+        results.push(compressed);
+    }
+
+    Ok(results)
+}
+
+/// Parallel counterpart to [`generate_face_thumbnails`] that maps each face box to its
+/// encoded thumbnail across a rayon thread pool, preserving input ordering. Each worker
+/// builds its own [`Resizer`] (it is `&mut`) while sharing the read-only source buffer.
+/// `max_threads` caps the degree of parallelism so embedders can bound peak memory when
+/// encoding many large crops at once; `None` uses the global rayon pool.
+#[cfg(feature = "parallel")]
+pub fn generate_face_thumbnails_parallel(
+    decoded: &DecodedImage,
+    face_boxes: &[FaceBox],
+    format: EncodedImageFormat,
+    max_threads: Option<usize>,
+) -> MlResult<Vec<Vec<u8>>> {
+    use rayon::prelude::*;
+
+    if face_boxes.is_empty() {
+        return Ok(Vec::new());
+    }
+    if decoded.dimensions.width == 0 || decoded.dimensions.height == 0 {
+        return Err(MlError::Decode(
+            "decoded image dimensions cannot be empty".to_string(),
+        ));
+    }
+
+    let source = fir_image_ref_from_decoded(decoded)?;
+    let image_width = decoded.dimensions.width as f64;
+    let image_height = decoded.dimensions.height as f64;
+
+    let render_all = || {
+        face_boxes
+            .par_iter()
+            .enumerate()
+            .map(|(index, face_box)| {
+                let mut resizer = Resizer::new();
+                render_face_thumbnail(
+                    &source,
+                    face_box,
+                    image_width,
+                    image_height,
+                    format,
+                    &mut resizer,
+                )
+                .map_err(|e| {
+                    MlError::InvalidRequest(format!("invalid face box at index {index}: {e}"))
+                })
+            })
+            .collect::<MlResult<Vec<Vec<u8>>>>()
+    };
+
+    match max_threads {
+        Some(threads) if threads > 0 => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|e| MlError::Runtime(format!("failed to build thumbnail thread pool: {e}")))?
+            .install(render_all),
+        _ => render_all(),
+    }
+}
+
+fn render_face_thumbnail(
+    source: &FirImageRef<'_>,
+    face_box: &FaceBox,
+    image_width: f64,
+    image_height: f64,
+    format: EncodedImageFormat,
+    resizer: &mut Resizer,
+) -> MlResult<Vec<u8>> {
+    let crop = compute_crop_rect(face_box, image_width, image_height)
+        .map_err(MlError::InvalidRequest)?;
+    let (target_width, target_height) =
+        dimensions_with_min_side(crop.output_width, crop.output_height)?;
+    let resized = resize_crop_with_fir(source, &crop, target_width, target_height, resizer)?;
+    encode_rgb(resized.buffer(), target_width, target_height, format)
+}
+
+/// Generate several thumbnails from a single decoded image in one pass, so callers can
+/// build a responsive set (e.g. a 32x32 crop, a 256x256 scale, and a full display size)
+/// without re-decoding the source. Each spec is encoded in its own
+/// [`ThumbnailSpec::format`]; outputs are returned in the order of `specs`.
+pub fn generate_thumbnails(
+    decoded: &DecodedImage,
+    specs: &[ThumbnailSpec],
+) -> MlResult<Vec<Vec<u8>>> {
+    if specs.is_empty() {
+        return Ok(Vec::new());
+    }
+    if decoded.dimensions.width == 0 || decoded.dimensions.height == 0 {
+        return Err(MlError::Decode(
+            "decoded image dimensions cannot be empty".to_string(),
+        ));
+    }
+
+    let source = fir_image_ref_from_decoded(decoded)?;
+    let mut resizer = Resizer::new();
+    let image_width = decoded.dimensions.width as f64;
+    let image_height = decoded.dimensions.height as f64;
+    let mut results = Vec::with_capacity(specs.len());
+
+    for (index, spec) in specs.iter().enumerate() {
+        if spec.width == 0 || spec.height == 0 {
+            return Err(MlError::InvalidRequest(format!(
+                "thumbnail spec at index {index} must have positive width and height"
+            )));
+        }
+        let crop = compute_thumbnail_crop(spec, image_width, image_height);
+        let resized = resize_crop_with_fir(
+            &source,
+            &crop,
+            crop.output_width,
+            crop.output_height,
+            &mut resizer,
         )?;
+        let compressed =
+            encode_rgb(resized.buffer(), crop.output_width, crop.output_height, spec.format)?;
         results.push(compressed);
     }
 
     Ok(results)
 }
 
+/// HDR-aware counterpart to [`generate_thumbnails`] that resizes and crops natively at
+/// 16-bit (`fast_image_resize`'s `U16x3` pixel type) before the final encode, so sources
+/// decoded via `decode_image16_from_path` keep their extra tonal precision all the way to
+/// a 16-bit PNG. Formats that can't carry 16 bits are quantized at the encode step only.
+pub fn generate_thumbnails16(
+    decoded: &DecodedImage16,
+    specs: &[ThumbnailSpec],
+) -> MlResult<Vec<Vec<u8>>> {
+    if specs.is_empty() {
+        return Ok(Vec::new());
+    }
+    if decoded.dimensions.width == 0 || decoded.dimensions.height == 0 {
+        return Err(MlError::Decode(
+            "decoded image dimensions cannot be empty".to_string(),
+        ));
+    }
+
+    let source = fir_image16_from_decoded(decoded)?;
+    let mut resizer = Resizer::new();
+    let image_width = decoded.dimensions.width as f64;
+    let image_height = decoded.dimensions.height as f64;
+    let mut results = Vec::with_capacity(specs.len());
+
+    for (index, spec) in specs.iter().enumerate() {
+        if spec.width == 0 || spec.height == 0 {
+            return Err(MlError::InvalidRequest(format!(
+                "thumbnail spec at index {index} must have positive width and height"
+            )));
+        }
+        let crop = compute_thumbnail_crop(spec, image_width, image_height);
+        let resized = resize_crop_with_fir16(
+            &source,
+            &crop,
+            crop.output_width,
+            crop.output_height,
+            &mut resizer,
+        )?;
+        let samples = samples_from_u16_image(&resized);
+        let compressed = encode_rgb16(
+            &samples,
+            crop.output_width,
+            crop.output_height,
+            spec.format,
+        )?;
+        results.push(compressed);
+    }
+
+    Ok(results)
+}
+
+fn compute_thumbnail_crop(spec: &ThumbnailSpec, image_width: f64, image_height: f64) -> CropRect {
+    match spec.method {
+        ThumbnailMethod::Scale => {
+            let scale =
+                (f64::from(spec.width) / image_width).min(f64::from(spec.height) / image_height);
+            let output_width = ((image_width * scale).round() as u32).max(1);
+            let output_height = ((image_height * scale).round() as u32).max(1);
+            CropRect {
+                x: 0.0,
+                y: 0.0,
+                width: image_width,
+                height: image_height,
+                output_width,
+                output_height,
+            }
+        }
+        ThumbnailMethod::Crop => {
+            let target_aspect = f64::from(spec.width) / f64::from(spec.height);
+            let source_aspect = image_width / image_height;
+            let (crop_width, crop_height) = if source_aspect > target_aspect {
+                (image_height * target_aspect, image_height)
+            } else {
+                (image_width, image_width / target_aspect)
+            };
+            let x = ((image_width - crop_width) / 2.0).max(0.0);
+            let y = ((image_height - crop_height) / 2.0).max(0.0);
+            CropRect {
+                x,
+                y,
+                width: crop_width.min(image_width),
+                height: crop_height.min(image_height),
+                output_width: spec.width,
+                output_height: spec.height,
+            }
+        }
+    }
+}
+
 fn fir_image_ref_from_decoded(decoded: &DecodedImage) -> MlResult<FirImageRef<'_>> {
     FirImageRef::new(
         decoded.dimensions.width,
@@ -83,6 +306,57 @@ fn fir_image_ref_from_decoded(decoded: &DecodedImage) -> MlResult<FirImageRef<'_
     .map_err(|e| MlError::Decode(format!("invalid decoded RGB buffer: {e}")))
 }
 
+fn fir_image16_from_decoded(decoded: &DecodedImage16) -> MlResult<FirImage<'static>> {
+    let expected = decoded.dimensions.width as usize * decoded.dimensions.height as usize * 3;
+    if decoded.rgb.len() != expected {
+        return Err(MlError::Decode(format!(
+            "invalid decoded RGB buffer length {}, expected {expected}",
+            decoded.rgb.len()
+        )));
+    }
+    // `fast_image_resize` takes a byte buffer; reinterpret the samples in native endianness
+    // so the owned image (and therefore the resize output) stays at full 16-bit precision.
+    let mut bytes = Vec::with_capacity(decoded.rgb.len() * 2);
+    for sample in &decoded.rgb {
+        bytes.extend_from_slice(&sample.to_ne_bytes());
+    }
+    FirImage::from_vec_u8(
+        decoded.dimensions.width,
+        decoded.dimensions.height,
+        bytes,
+        PixelType::U16x3,
+    )
+    .map_err(|e| MlError::Decode(format!("invalid decoded RGB buffer: {e}")))
+}
+
+fn resize_crop_with_fir16(
+    source: &impl IntoImageView,
+    crop: &CropRect,
+    target_width: u32,
+    target_height: u32,
+    resizer: &mut Resizer,
+) -> MlResult<FirImage<'static>> {
+    let mut resized = FirImage::new(target_width, target_height, PixelType::U16x3);
+    let filter = select_resize_filter(crop, target_width, target_height);
+    let options = ResizeOptions::new()
+        .crop(crop.x, crop.y, crop.width, crop.height)
+        .resize_alg(ResizeAlg::Convolution(filter));
+    resizer
+        .resize(source, &mut resized, Some(&options))
+        .map_err(|e| MlError::Postprocess(format!("failed to resize face thumbnail crop: {e}")))?;
+    Ok(resized)
+}
+
+/// Re-read a `U16x3` [`FirImage`] byte buffer back into `u16` samples (native endianness,
+/// matching [`fir_image16_from_decoded`]).
+fn samples_from_u16_image(image: &FirImage<'static>) -> Vec<u16> {
+    image
+        .buffer()
+        .chunks_exact(2)
+        .map(|pair| u16::from_ne_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
 fn compute_crop_rect(
     face_box: &FaceBox,
     image_width: f64,
@@ -233,10 +507,15 @@ mod tests {
     use image::ImageFormat;
 
     use super::{
-        FaceBox, compute_crop_rect, dimensions_with_min_side, generate_face_thumbnails,
-        select_resize_filter,
+        FaceBox, ThumbnailMethod, ThumbnailSpec, compute_crop_rect, dimensions_with_min_side,
+        generate_face_thumbnails, generate_thumbnails, generate_thumbnails16, select_resize_filter,
+    };
+    use crate::image::image_compression::{EncodedImageFormat, FACE_THUMBNAIL_JPEG_QUALITY};
+    use crate::ml::types::{DecodedImage, DecodedImage16, Dimensions};
+
+    const TEST_JPEG: EncodedImageFormat = EncodedImageFormat::Jpeg {
+        quality: FACE_THUMBNAIL_JPEG_QUALITY,
     };
-    use crate::ml::types::{DecodedImage, Dimensions};
 
     #[test]
     fn compute_crop_rect_matches_canvas_math_for_center_box() {
@@ -292,7 +571,7 @@ mod tests {
         ];
 
         let thumbnails =
-            generate_face_thumbnails(&decoded, &face_boxes).expect("thumbnails should generate");
+            generate_face_thumbnails(&decoded, &face_boxes, TEST_JPEG).expect("thumbnails should generate");
 
         assert_eq!(thumbnails.len(), 2);
         for bytes in thumbnails {
@@ -314,7 +593,7 @@ mod tests {
             height: 0.3,
         }];
 
-        let result = generate_face_thumbnails(&decoded, &face_boxes);
+        let result = generate_face_thumbnails(&decoded, &face_boxes, TEST_JPEG);
 
         assert!(result.is_err());
     }
@@ -396,7 +675,7 @@ mod tests {
         }];
 
         let thumbnails =
-            generate_face_thumbnails(&decoded, &face_boxes).expect("thumbnails should generate");
+            generate_face_thumbnails(&decoded, &face_boxes, TEST_JPEG).expect("thumbnails should generate");
         assert_eq!(thumbnails.len(), 1);
 
         let decoded_jpeg = image::load_from_memory_with_format(&thumbnails[0], ImageFormat::Jpeg)
@@ -405,6 +684,118 @@ mod tests {
         assert_eq!(short_side, 512);
     }
 
+    #[test]
+    fn generate_thumbnails_scale_fits_inside_box_and_crop_fills_it() {
+        let decoded = synthetic_decoded_image(200, 100);
+        let specs = vec![
+            ThumbnailSpec {
+                width: 64,
+                height: 64,
+                method: ThumbnailMethod::Scale,
+                format: TEST_JPEG,
+            },
+            ThumbnailSpec {
+                width: 64,
+                height: 64,
+                method: ThumbnailMethod::Crop,
+                format: TEST_JPEG,
+            },
+        ];
+
+        let thumbnails = generate_thumbnails(&decoded, &specs).expect("thumbnails should generate");
+        assert_eq!(thumbnails.len(), 2);
+
+        // Scale fits a 2:1 image inside a 64x64 box -> 64x32.
+        let scaled = image::load_from_memory_with_format(&thumbnails[0], ImageFormat::Jpeg)
+            .expect("scaled thumbnail should decode");
+        assert_eq!(scaled.width(), 64);
+        assert_eq!(scaled.height(), 32);
+
+        // Crop fills the box exactly.
+        let cropped = image::load_from_memory_with_format(&thumbnails[1], ImageFormat::Jpeg)
+            .expect("cropped thumbnail should decode");
+        assert_eq!(cropped.width(), 64);
+        assert_eq!(cropped.height(), 64);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn generate_face_thumbnails_parallel_matches_serial_ordering() {
+        use super::generate_face_thumbnails_parallel;
+
+        let decoded = synthetic_decoded_image(64, 48);
+        let face_boxes = vec![
+            FaceBox {
+                x: 0.1,
+                y: 0.1,
+                width: 0.3,
+                height: 0.3,
+            },
+            FaceBox {
+                x: 0.5,
+                y: 0.2,
+                width: 0.4,
+                height: 0.5,
+            },
+        ];
+
+        let serial = generate_face_thumbnails(&decoded, &face_boxes, TEST_JPEG)
+            .expect("serial thumbnails should generate");
+        let parallel = generate_face_thumbnails_parallel(&decoded, &face_boxes, TEST_JPEG, Some(2))
+            .expect("parallel thumbnails should generate");
+
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn generate_thumbnails_rejects_zero_sized_spec() {
+        let decoded = synthetic_decoded_image(32, 32);
+        let specs = vec![ThumbnailSpec {
+            width: 0,
+            height: 16,
+            method: ThumbnailMethod::Scale,
+            format: TEST_JPEG,
+        }];
+
+        assert!(generate_thumbnails(&decoded, &specs).is_err());
+    }
+
+    #[test]
+    fn generate_thumbnails16_emits_16bit_png_at_requested_size() {
+        let mut rgb = Vec::with_capacity((8 * 8 * 3) as usize);
+        for y in 0..8u32 {
+            for x in 0..8u32 {
+                rgb.push((x * 8000) as u16);
+                rgb.push((y * 8000) as u16);
+                rgb.push(40_000);
+            }
+        }
+        let decoded = DecodedImage16 {
+            dimensions: Dimensions {
+                width: 8,
+                height: 8,
+            },
+            rgb,
+            source_bit_depth: 16,
+        };
+        let specs = vec![ThumbnailSpec {
+            width: 4,
+            height: 4,
+            method: ThumbnailMethod::Crop,
+            format: EncodedImageFormat::Png { optimize: false },
+        }];
+
+        let thumbnails = generate_thumbnails16(&decoded, &specs).expect("thumbnails should generate");
+        assert_eq!(thumbnails.len(), 1);
+
+        let decoded_png = image::load_from_memory_with_format(&thumbnails[0], ImageFormat::Png)
+            .expect("thumbnail bytes should decode as PNG");
+        assert_eq!(decoded_png.width(), 4);
+        assert_eq!(decoded_png.height(), 4);
+        // The output kept 16-bit precision end to end.
+        assert_eq!(decoded_png.color(), image::ColorType::Rgb16);
+    }
+
     fn synthetic_decoded_image(width: u32, height: u32) -> DecodedImage {
         let mut rgb = Vec::with_capacity((width * height * 3) as usize);
         for y in 0..height {
@@ -417,6 +808,7 @@ mod tests {
         DecodedImage {
             dimensions: Dimensions { width, height },
             rgb,
+            source_bit_depth: 8,
         }
     }
 }
@@ -4,6 +4,7 @@ use fast_image_resize::{
 
 use crate::ml::{
     error::{MlError, MlResult},
+    normalize::affine_normalize,
     types::DecodedImage,
 };
 
@@ -26,21 +27,61 @@ pub fn preprocess_yolo(decoded: &DecodedImage) -> MlResult<(Vec<f32>, usize, usi
     let scaled_width = (src_w * scale).round().clamp(0.0, YOLO_INPUT_WIDTH as f32) as usize;
     let scaled_height = (src_h * scale).round().clamp(0.0, YOLO_INPUT_HEIGHT as f32) as usize;
 
-    let mut output = vec![0f32; 3 * YOLO_INPUT_WIDTH * YOLO_INPUT_HEIGHT];
     let green_offset = YOLO_INPUT_WIDTH * YOLO_INPUT_HEIGHT;
     let blue_offset = 2 * YOLO_INPUT_WIDTH * YOLO_INPUT_HEIGHT;
 
-    for y in 0..YOLO_INPUT_HEIGHT {
-        for x in 0..YOLO_INPUT_WIDTH {
-            let idx = y * YOLO_INPUT_WIDTH + x;
-            let rgb = if x >= scaled_width || y >= scaled_height {
-                [PAD_VALUE, PAD_VALUE, PAD_VALUE]
-            } else {
-                sample_bilinear_rgb(decoded, x as f32 / scale, y as f32 / scale)
-            };
-            output[idx] = rgb[0] / 255.0;
-            output[idx + green_offset] = rgb[1] / 255.0;
-            output[idx + blue_offset] = rgb[2] / 255.0;
+    // Pre-fill with the letterbox pad value so the region outside the scaled image
+    // stays at 114/255 without a per-pixel branch below.
+    let mut output = vec![PAD_VALUE / 255.0; 3 * YOLO_INPUT_WIDTH * YOLO_INPUT_HEIGHT];
+
+    if scaled_width > 0 && scaled_height > 0 {
+        let src_image = FirImage::from_vec_u8(
+            decoded.dimensions.width,
+            decoded.dimensions.height,
+            decoded.rgb.clone(),
+            PixelType::U8x3,
+        )
+        .map_err(|e| MlError::Preprocess(format!("failed to create FIR source image: {e}")))?;
+
+        let mut resized_image =
+            FirImage::new(scaled_width as u32, scaled_height as u32, PixelType::U8x3);
+        let mut resizer = Resizer::new();
+        let options =
+            ResizeOptions::new().resize_alg(ResizeAlg::Convolution(FilterType::Bilinear));
+        resizer
+            .resize(&src_image, &mut resized_image, Some(&options))
+            .map_err(|e| MlError::Preprocess(format!("failed to resize YOLO image input: {e}")))?;
+
+        let resized = resized_image.buffer();
+
+        // Split the interleaved RGB into contiguous channel planes so the HWC->CHW
+        // reshuffle becomes a set of contiguous rows that the SIMD affine pass (`/255`)
+        // can normalize directly, instead of a scalar per-pixel triple-store.
+        let plane_pixels = scaled_width * scaled_height;
+        let mut red = vec![0u8; plane_pixels];
+        let mut green = vec![0u8; plane_pixels];
+        let mut blue = vec![0u8; plane_pixels];
+        for i in 0..plane_pixels {
+            red[i] = resized[i * 3];
+            green[i] = resized[i * 3 + 1];
+            blue[i] = resized[i * 3 + 2];
+        }
+
+        for (plane, channel_offset) in [
+            (&red, 0),
+            (&green, green_offset),
+            (&blue, blue_offset),
+        ] {
+            for y in 0..scaled_height {
+                let src_row = &plane[y * scaled_width..(y + 1) * scaled_width];
+                let dst_start = channel_offset + y * YOLO_INPUT_WIDTH;
+                affine_normalize(
+                    src_row,
+                    1.0 / 255.0,
+                    0.0,
+                    &mut output[dst_start..dst_start + scaled_width],
+                );
+            }
         }
     }
 
@@ -99,48 +140,43 @@ pub fn preprocess_clip(decoded: &DecodedImage) -> MlResult<Vec<f32>> {
     Ok(output)
 }
 
-pub fn sample_bilinear_rgb(decoded: &DecodedImage, fx: f32, fy: f32) -> [f32; 3] {
-    let max_x = (decoded.dimensions.width.saturating_sub(1)) as f32;
-    let max_y = (decoded.dimensions.height.saturating_sub(1)) as f32;
-    let fx = fx.clamp(0.0, max_x);
-    let fy = fy.clamp(0.0, max_y);
-
-    let x0 = fx.floor() as i32;
-    let x1 = fx.ceil() as i32;
-    let y0 = fy.floor() as i32;
-    let y1 = fy.ceil() as i32;
-    let dx = fx - x0 as f32;
-    let dy = fy - y0 as f32;
-    let dx1 = 1.0 - dx;
-    let dy1 = 1.0 - dy;
-
-    let p1 = read_rgb(decoded, x0, y0);
-    let p2 = read_rgb(decoded, x1, y0);
-    let p3 = read_rgb(decoded, x0, y1);
-    let p4 = read_rgb(decoded, x1, y1);
-
-    let blend = |v1: f32, v2: f32, v3: f32, v4: f32| -> f32 {
-        v1 * dx1 * dy1 + v2 * dx * dy1 + v3 * dx1 * dy + v4 * dx * dy
-    };
-
-    [
-        blend(p1[0], p2[0], p3[0], p4[0]),
-        blend(p1[1], p2[1], p3[1], p4[1]),
-        blend(p1[2], p2[2], p3[2], p4[2]),
-    ]
-}
+#[cfg(test)]
+mod tests {
+    use super::{PAD_VALUE, YOLO_INPUT_WIDTH, preprocess_yolo};
+    use crate::ml::types::{DecodedImage, Dimensions};
 
-pub fn read_rgb(decoded: &DecodedImage, x: i32, y: i32) -> [f32; 3] {
-    let width = decoded.dimensions.width as i32;
-    let height = decoded.dimensions.height as i32;
-    if x < 0 || y < 0 || x >= width || y >= height {
-        return [PAD_VALUE, PAD_VALUE, PAD_VALUE];
+    fn solid_decoded(width: u32, height: u32, rgb: [u8; 3]) -> DecodedImage {
+        let mut buffer = Vec::with_capacity((width * height * 3) as usize);
+        for _ in 0..(width * height) {
+            buffer.extend_from_slice(&rgb);
+        }
+        DecodedImage {
+            dimensions: Dimensions { width, height },
+            rgb: buffer,
+            source_bit_depth: 8,
+        }
     }
 
-    let idx = ((y as usize * decoded.dimensions.width as usize) + x as usize) * 3;
-    [
-        decoded.rgb[idx] as f32,
-        decoded.rgb[idx + 1] as f32,
-        decoded.rgb[idx + 2] as f32,
-    ]
+    #[test]
+    fn preprocess_yolo_letterboxes_uniform_image() {
+        // 64x32 scales by 10 -> 640x320 occupied, bottom half padded.
+        let decoded = solid_decoded(64, 32, [200, 200, 200]);
+        let (output, scaled_width, scaled_height) =
+            preprocess_yolo(&decoded).expect("preprocess should succeed");
+
+        assert_eq!(scaled_width, 640);
+        assert_eq!(scaled_height, 320);
+
+        let plane = YOLO_INPUT_WIDTH * YOLO_INPUT_WIDTH;
+        let expected = 200.0 / 255.0;
+        for channel in 0..3 {
+            // A pixel inside the scaled region round-trips to the source constant.
+            let inside = channel * plane + 100 * YOLO_INPUT_WIDTH + 100;
+            assert!((output[inside] - expected).abs() < 1e-3);
+
+            // The padded region below the scaled image stays at 114/255.
+            let padded = channel * plane + 400 * YOLO_INPUT_WIDTH + 100;
+            assert!((output[padded] - PAD_VALUE / 255.0).abs() < 1e-6);
+        }
+    }
 }
@@ -4,7 +4,9 @@ use once_cell::sync::Lazy;
 use ort::Session;
 
 use crate::ml::{
+    embedding_cache::{EmbeddingCache, EmbeddingCacheConfig, TensorHash},
     error::{MlError, MlResult},
+    face::detect::NmsConfig,
     onnx,
 };
 
@@ -32,10 +34,55 @@ pub struct ModelPaths {
     pub clip_image: String,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Brown–Conrady lens-distortion coefficients used to undistort a frame before face
+/// detection. An all-zero configuration (the default) is the identity and is skipped, so
+/// callers that don't supply coefficients are unaffected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LensDistortionConfig {
+    pub k1: f32,
+    pub k2: f32,
+    pub k3: f32,
+    pub p1: f32,
+    pub p2: f32,
+    /// Principal point in pixels; defaults to the image center when `None`.
+    pub principal_point: Option<(f32, f32)>,
+    /// Focal length in pixels `(fx, fy)`; defaults to the image diagonal when `None`
+    /// (derived from EXIF by the caller when available).
+    pub focal: Option<(f32, f32)>,
+}
+
+impl Default for LensDistortionConfig {
+    fn default() -> Self {
+        Self {
+            k1: 0.0,
+            k2: 0.0,
+            k3: 0.0,
+            p1: 0.0,
+            p2: 0.0,
+            principal_point: None,
+            focal: None,
+        }
+    }
+}
+
+impl LensDistortionConfig {
+    /// True when every coefficient is zero, i.e. the correction is a no-op.
+    pub fn is_identity(&self) -> bool {
+        self.k1 == 0.0
+            && self.k2 == 0.0
+            && self.k3 == 0.0
+            && self.p1 == 0.0
+            && self.p2 == 0.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct MlRuntimeConfig {
     pub model_paths: ModelPaths,
     pub provider_policy: ExecutionProviderPolicy,
+    pub lens_distortion: LensDistortionConfig,
+    pub nms: NmsConfig,
+    pub embedding_cache: EmbeddingCacheConfig,
 }
 
 #[derive(Debug)]
@@ -43,6 +90,7 @@ pub struct MlRuntime {
     face_detection: Option<Session>,
     face_embedding: Option<Session>,
     clip_image: Option<Session>,
+    embedding_cache: EmbeddingCache,
 }
 
 #[derive(Debug)]
@@ -60,10 +108,12 @@ fn create_runtime(config: &MlRuntimeConfig) -> MlResult<MlRuntime> {
         build_optional_session(&config.model_paths.face_embedding, &config.provider_policy)?;
     let clip_image =
         build_optional_session(&config.model_paths.clip_image, &config.provider_policy)?;
+    let embedding_cache = EmbeddingCache::new(&config.embedding_cache);
     Ok(MlRuntime {
         face_detection,
         face_embedding,
         clip_image,
+        embedding_cache,
     })
 }
 
@@ -104,6 +154,23 @@ impl MlRuntime {
             )
         })
     }
+
+    /// Look up a cached embedding for a previously seen input tensor.
+    pub fn cached_embedding(&self, key: &TensorHash) -> Option<Vec<f32>> {
+        self.embedding_cache.get(key)
+    }
+
+    /// Record a freshly computed embedding so a later re-index of the same content skips the
+    /// session run.
+    pub fn cache_embedding(&mut self, key: TensorHash, embedding: Vec<f32>) {
+        self.embedding_cache.insert(key, embedding);
+    }
+
+    /// Persist any newly cached embeddings to disk. Best-effort; callers log but don't fail on
+    /// a flush error since the in-memory cache is still valid.
+    pub fn flush_embedding_cache(&mut self) -> MlResult<()> {
+        self.embedding_cache.flush()
+    }
 }
 
 fn lock_runtime() -> std::sync::MutexGuard<'static, Option<RuntimeState>> {
@@ -8,6 +8,41 @@ pub struct Dimensions {
 pub struct DecodedImage {
     pub dimensions: Dimensions,
     pub rgb: Vec<u8>,
+    /// Per-channel bit depth of the original source (8 for ordinary JPEG/PNG, 16 for
+    /// 16-bit PNG/TIFF, 32 for HDR float sources). The `rgb` buffer is always 8-bit;
+    /// this lets callers tell whether precision was reduced during decode.
+    pub source_bit_depth: u8,
+}
+
+/// High-bit-depth counterpart to [`DecodedImage`] that keeps the source precision in a
+/// `Vec<u16>` RGB buffer instead of quantizing to 8-bit at decode time. The HDR-aware
+/// decode + resize path operates natively at 16-bit (see `fast_image_resize`'s
+/// `U16x3` pixel type) and only narrows to 8-bit at the final encode step when the
+/// output format can't carry the extra bits.
+#[derive(Clone, Debug)]
+pub struct DecodedImage16 {
+    pub dimensions: Dimensions,
+    pub rgb: Vec<u16>,
+    /// Per-channel bit depth of the original source (8, 16, or 32 for HDR float). The
+    /// buffer is widened to `u16`; this records whether that widening added real bits.
+    pub source_bit_depth: u8,
+}
+
+impl DecodedImage16 {
+    /// Quantize down to an 8-bit [`DecodedImage`] with correct rounding (not a naive
+    /// high-byte truncation), matching what `image`'s `to_rgb8()` does for 16-bit inputs.
+    pub fn to_rgb8(&self) -> DecodedImage {
+        let rgb = self
+            .rgb
+            .iter()
+            .map(|&channel| ((channel as u32 * 255 + 32_767) / 65_535) as u8)
+            .collect();
+        DecodedImage {
+            dimensions: self.dimensions.clone(),
+            rgb,
+            source_bit_depth: self.source_bit_depth,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -23,6 +58,16 @@ pub struct AlignmentResult {
     pub center: [f32; 2],
     pub size: f32,
     pub rotation: f32,
+    /// Number of the five landmarks that agreed with the robust similarity fit. A low
+    /// count (from a profile or occluded face) lets downstream code down-weight the
+    /// alignment. Equals 5 for a clean frontal face.
+    pub inlier_count: u32,
+    /// Continuous head-pose Euler angles in degrees, recovered from the five landmarks by
+    /// a weak-perspective POS solve. All zero when the solve was ill-conditioned and the
+    /// coarse heuristic was used instead.
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
 }
 
 #[derive(Clone, Debug)]
@@ -32,6 +77,7 @@ pub struct FaceResult {
     pub alignment: AlignmentResult,
     pub embedding: Vec<f32>,
     pub face_id: String,
+    pub blurhash: Option<String>,
 }
 
 #[derive(Clone, Debug)]
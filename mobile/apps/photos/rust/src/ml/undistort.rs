@@ -0,0 +1,195 @@
+use image::{Rgb, RgbImage};
+use imageproc::geometric_transformations::{Interpolation, warp_with};
+
+use crate::ml::{
+    error::{MlError, MlResult},
+    runtime::LensDistortionConfig,
+    types::{DecodedImage, Dimensions},
+};
+
+/// Undistort a decoded frame with a Brown–Conrady model before face detection, so radial
+/// distortion from wide-angle phone and action cameras doesn't bend the detected box or
+/// shift the five landmarks fed into alignment.
+///
+/// For each output (undistorted) pixel the normalized coordinate `(x - cx) / fx` is pushed
+/// through the forward distortion model to find the source (distorted) pixel, which is then
+/// sampled bilinearly. An identity configuration is returned unchanged so existing callers
+/// pay nothing.
+pub fn correct_lens_distortion(
+    decoded: &DecodedImage,
+    config: &LensDistortionConfig,
+) -> MlResult<DecodedImage> {
+    if config.is_identity() {
+        return Ok(decoded.clone());
+    }
+    if decoded.dimensions.width == 0 || decoded.dimensions.height == 0 {
+        return Err(MlError::Preprocess(
+            "image dimensions cannot be zero".to_string(),
+        ));
+    }
+
+    let width = decoded.dimensions.width;
+    let height = decoded.dimensions.height;
+    let source = RgbImage::from_raw(width, height, decoded.rgb.clone()).ok_or_else(|| {
+        MlError::Preprocess("decoded RGB buffer does not match image dimensions".to_string())
+    })?;
+
+    let (cx, cy) = config
+        .principal_point
+        .unwrap_or((width as f32 / 2.0, height as f32 / 2.0));
+    let diagonal = (width as f32).hypot(height as f32);
+    let (fx, fy) = config.focal.unwrap_or((diagonal, diagonal));
+    if fx == 0.0 || fy == 0.0 {
+        return Err(MlError::Preprocess(
+            "lens focal length cannot be zero".to_string(),
+        ));
+    }
+    let LensDistortionConfig {
+        k1, k2, k3, p1, p2, ..
+    } = *config;
+
+    let warped = warp_with(
+        &source,
+        |x, y| {
+            let xn = (x - cx) / fx;
+            let yn = (y - cy) / fy;
+            let r2 = xn * xn + yn * yn;
+            let radial = 1.0 + k1 * r2 + k2 * r2 * r2 + k3 * r2 * r2 * r2;
+            let x_d = xn * radial + 2.0 * p1 * xn * yn + p2 * (r2 + 2.0 * xn * xn);
+            let y_d = yn * radial + p1 * (r2 + 2.0 * yn * yn) + 2.0 * p2 * xn * yn;
+            (x_d * fx + cx, y_d * fy + cy)
+        },
+        Interpolation::Bilinear,
+        Rgb([114, 114, 114]),
+    );
+
+    Ok(DecodedImage {
+        dimensions: Dimensions { width, height },
+        rgb: warped.into_raw(),
+        source_bit_depth: decoded.source_bit_depth,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::correct_lens_distortion;
+    use crate::ml::runtime::LensDistortionConfig;
+    use crate::ml::types::{DecodedImage, Dimensions};
+
+    fn gradient(width: u32, height: u32) -> DecodedImage {
+        let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                rgb.push((x % 256) as u8);
+                rgb.push((y % 256) as u8);
+                rgb.push(128);
+            }
+        }
+        DecodedImage {
+            dimensions: Dimensions { width, height },
+            rgb,
+            source_bit_depth: 8,
+        }
+    }
+
+    #[test]
+    fn identity_config_returns_input_unchanged() {
+        let decoded = gradient(16, 12);
+        let corrected = correct_lens_distortion(&decoded, &LensDistortionConfig::default())
+            .expect("identity correction should succeed");
+        assert_eq!(corrected.rgb, decoded.rgb);
+    }
+
+    #[test]
+    fn non_identity_config_preserves_dimensions() {
+        let decoded = gradient(16, 12);
+        let config = LensDistortionConfig {
+            k1: -0.2,
+            ..LensDistortionConfig::default()
+        };
+        let corrected =
+            correct_lens_distortion(&decoded, &config).expect("correction should succeed");
+        assert_eq!(corrected.dimensions, decoded.dimensions);
+        assert_eq!(corrected.rgb.len(), decoded.rgb.len());
+    }
+
+    #[test]
+    fn distortion_samples_the_position_predicted_by_the_documented_formula() {
+        // Geometry chosen so the marker patch and its floor/ceil neighbours stay well
+        // inside a 101x101 frame with no accidentally-zero term in the formula.
+        const WIDTH: u32 = 101;
+        const HEIGHT: u32 = 101;
+        const CX: f32 = 50.0;
+        const CY: f32 = 50.0;
+        const FX: f32 = 50.0;
+        const FY: f32 = 50.0;
+        const OX: u32 = 70;
+        const OY: u32 = 50;
+        const BACKGROUND: [u8; 3] = [10, 20, 30];
+        const MARKER: [u8; 3] = [250, 5, 200];
+
+        let config = LensDistortionConfig {
+            k1: 0.1,
+            k2: 0.05,
+            k3: 0.0,
+            p1: 0.02,
+            p2: 0.03,
+            principal_point: Some((CX, CY)),
+            focal: Some((FX, FY)),
+        };
+
+        // Reproduce the module's documented
+        // `x_d = x(1 + k1 r^2 + k2 r^4 + k3 r^6) + 2 p1 x y + p2 (r^2 + 2x^2)` (and the
+        // analogous y_d) by hand, independently of the implementation, to find which
+        // distorted-source pixel the undistorted output pixel (OX, OY) should sample.
+        let xn = (OX as f32 - CX) / FX;
+        let yn = (OY as f32 - CY) / FY;
+        let r2 = xn * xn + yn * yn;
+        let radial = 1.0 + config.k1 * r2 + config.k2 * r2 * r2 + config.k3 * r2 * r2 * r2;
+        let x_d = xn * radial + 2.0 * config.p1 * xn * yn + config.p2 * (r2 + 2.0 * xn * xn);
+        let y_d = yn * radial + config.p1 * (r2 + 2.0 * yn * yn) + 2.0 * config.p2 * xn * yn;
+        let sx = x_d * FX + CX;
+        let sy = y_d * FY + CY;
+
+        // Bracket the expected sample with a solid-color patch so bilinear interpolation
+        // over it returns that color exactly, regardless of the fractional sample position.
+        let patch_x0 = sx.floor() as i64 - 1;
+        let patch_y0 = sy.floor() as i64 - 1;
+        let patch_x1 = sx.ceil() as i64 + 1;
+        let patch_y1 = sy.ceil() as i64 + 1;
+
+        let mut rgb = Vec::with_capacity((WIDTH * HEIGHT * 3) as usize);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let in_patch = (x as i64) >= patch_x0
+                    && (x as i64) <= patch_x1
+                    && (y as i64) >= patch_y0
+                    && (y as i64) <= patch_y1;
+                rgb.extend_from_slice(if in_patch { &MARKER } else { &BACKGROUND });
+            }
+        }
+        let decoded = DecodedImage {
+            dimensions: Dimensions {
+                width: WIDTH,
+                height: HEIGHT,
+            },
+            rgb,
+            source_bit_depth: 8,
+        };
+
+        let corrected =
+            correct_lens_distortion(&decoded, &config).expect("correction should succeed");
+        let idx = ((OY * WIDTH + OX) * 3) as usize;
+        assert_eq!(
+            &corrected.rgb[idx..idx + 3],
+            &MARKER,
+            "output pixel should sample the marker patch at the position the documented \
+             Brown-Conrady formula predicts, not some other (e.g. sign-flipped) location"
+        );
+
+        // Sanity check that the match isn't coincidental: a pixel away from both the patch
+        // and its corresponding source location should still read background.
+        let far_idx = ((10 * WIDTH + 10) * 3) as usize;
+        assert_eq!(&corrected.rgb[far_idx..far_idx + 3], &BACKGROUND);
+    }
+}
@@ -0,0 +1,456 @@
+use fast_image_resize::{
+    FilterType, PixelType, ResizeAlg, ResizeOptions, Resizer, images::Image as FirImage,
+};
+
+use crate::ml::{
+    clip::image::run_clip_image,
+    error::{MlError, MlResult},
+    face::detect::{NmsConfig, run_face_detection},
+    runtime::MlRuntime,
+    types::{ClipResult, DecodedImage, FaceDetection},
+};
+
+/// One decoded video frame paired with its presentation timestamp in milliseconds from the
+/// start of the clip. The frame source yields these in presentation order.
+pub struct VideoFrame {
+    pub timestamp_ms: f64,
+    pub image: DecodedImage,
+}
+
+/// A forward-only stream of decoded frames. The concrete decoder lives behind the `video`
+/// feature (see [`open_video_source`]); the keyframe selection and ML passes are written
+/// against this trait so they can be exercised without a real video file.
+pub trait VideoFrameSource {
+    /// Return the next frame in presentation order, or `None` at end of stream.
+    fn next_frame(&mut self) -> MlResult<Option<VideoFrame>>;
+}
+
+/// Knobs for [`select_keyframes`]. The defaults extract a handful of representative frames
+/// from a typical short clip without flooding the detector with near-duplicates.
+#[derive(Clone, Debug)]
+pub struct VideoKeyframeOptions {
+    /// Mean absolute luma difference (0–255 scale) between a frame and its predecessor above
+    /// which the frame is treated as a scene change and becomes a keyframe candidate.
+    pub scene_change_threshold: f32,
+    /// Minimum gap between two selected keyframes, so a burst of scene changes (e.g. a hard
+    /// cut followed by motion) still yields at most one frame per window.
+    pub min_spacing_ms: f64,
+    /// Hard cap on selected frames, to bound work on long clips regardless of content.
+    pub max_frames: usize,
+    /// Width/height the frames are downscaled to before the luma comparison. Small is fine —
+    /// the metric only needs gross structure, and a tiny target keeps the diff cheap.
+    pub sample_size: u32,
+}
+
+impl Default for VideoKeyframeOptions {
+    fn default() -> Self {
+        Self {
+            scene_change_threshold: 12.0,
+            min_spacing_ms: 1000.0,
+            max_frames: 32,
+            sample_size: 32,
+        }
+    }
+}
+
+/// Which analyses to run on each selected keyframe. Mirrors the per-image `run_faces` /
+/// `run_clip` switches on [`crate::api::ml_indexing_api::AnalyzeImageRequest`].
+#[derive(Clone, Debug)]
+pub struct VideoOptions {
+    pub keyframe: VideoKeyframeOptions,
+    pub run_faces: bool,
+    pub run_clip: bool,
+    /// Suppression applied to each keyframe's detections, mirroring the still-image path.
+    pub nms: NmsConfig,
+}
+
+impl Default for VideoOptions {
+    fn default() -> Self {
+        Self {
+            keyframe: VideoKeyframeOptions::default(),
+            run_faces: true,
+            run_clip: true,
+            nms: NmsConfig::default(),
+        }
+    }
+}
+
+/// Analysis of a single keyframe: its timestamp plus whichever outputs were requested.
+pub struct VideoKeyframeResult {
+    pub timestamp_ms: f64,
+    pub faces: Option<Vec<FaceDetection>>,
+    pub clip: Option<ClipResult>,
+}
+
+/// Decode `path`, pick representative keyframes, and run the requested ML sessions on each.
+///
+/// Frames are threaded through the same [`MlRuntime`] sessions used for still images, so a
+/// video is analyzed as a small sequence of stills chosen by scene change rather than every
+/// decoded frame. Returns one [`VideoKeyframeResult`] per selected frame, in presentation
+/// order.
+pub fn run_video(
+    runtime: &mut MlRuntime,
+    path: &str,
+    opts: &VideoOptions,
+) -> MlResult<Vec<VideoKeyframeResult>> {
+    let source = open_video_source(path)?;
+    run_video_with_source(runtime, source, opts)
+}
+
+/// [`run_video`] against an already-opened frame source. Kept separate so the selection and
+/// inference path can be driven by a synthetic source in tests.
+pub fn run_video_with_source<S: VideoFrameSource>(
+    runtime: &mut MlRuntime,
+    source: S,
+    opts: &VideoOptions,
+) -> MlResult<Vec<VideoKeyframeResult>> {
+    let keyframes = select_keyframes(source, &opts.keyframe)?;
+    let mut results = Vec::with_capacity(keyframes.len());
+    for frame in keyframes {
+        let faces = if opts.run_faces {
+            Some(run_face_detection(runtime, &frame.image, &opts.nms)?)
+        } else {
+            None
+        };
+        let clip = if opts.run_clip {
+            Some(run_clip_image(runtime, &frame.image)?)
+        } else {
+            None
+        };
+        results.push(VideoKeyframeResult {
+            timestamp_ms: frame.timestamp_ms,
+            faces,
+            clip,
+        });
+    }
+    Ok(results)
+}
+
+/// Walk the frame source and keep only frames that differ enough from the immediately
+/// preceding frame and are far enough in time from the last kept frame. The first decoded frame always seeds the set, and
+/// selection stops once `max_frames` is reached.
+pub fn select_keyframes<S: VideoFrameSource>(
+    mut source: S,
+    opts: &VideoKeyframeOptions,
+) -> MlResult<Vec<VideoFrame>> {
+    let mut selected: Vec<VideoFrame> = Vec::new();
+    let mut reference_luma: Option<Vec<u8>> = None;
+    let mut last_selected_ms: Option<f64> = None;
+
+    while let Some(frame) = source.next_frame()? {
+        if selected.len() >= opts.max_frames {
+            break;
+        }
+
+        let luma = downscaled_luma(&frame.image, opts.sample_size)?;
+        let is_scene_change = match &reference_luma {
+            // The first frame has nothing to compare against and always anchors the set.
+            None => true,
+            Some(previous) => mean_absolute_difference(previous, &luma) >= opts.scene_change_threshold,
+        };
+        let spaced = last_selected_ms
+            .map(|last| frame.timestamp_ms - last >= opts.min_spacing_ms)
+            .unwrap_or(true);
+
+        if is_scene_change && spaced {
+            last_selected_ms = Some(frame.timestamp_ms);
+            selected.push(frame);
+        }
+        // Compare against the immediately preceding frame, not the last selected one, so only
+        // change between consecutive frames counts; a slow pan whose per-frame change stays
+        // under the threshold is not selected, however far it drifts in total.
+        reference_luma = Some(luma);
+    }
+
+    Ok(selected)
+}
+
+/// Downscale to `size`×`size` and convert to a single-channel luma buffer for comparison.
+fn downscaled_luma(image: &DecodedImage, size: u32) -> MlResult<Vec<u8>> {
+    if image.dimensions.width == 0 || image.dimensions.height == 0 {
+        return Err(MlError::Preprocess(
+            "video frame dimensions cannot be zero".to_string(),
+        ));
+    }
+
+    let source = FirImage::from_vec_u8(
+        image.dimensions.width,
+        image.dimensions.height,
+        image.rgb.clone(),
+        PixelType::U8x3,
+    )
+    .map_err(|e| MlError::Preprocess(format!("failed to create FIR source image: {e}")))?;
+
+    let mut resized = FirImage::new(size, size, PixelType::U8x3);
+    let mut resizer = Resizer::new();
+    let options = ResizeOptions::new().resize_alg(ResizeAlg::Convolution(FilterType::Bilinear));
+    resizer
+        .resize(&source, &mut resized, Some(&options))
+        .map_err(|e| MlError::Preprocess(format!("failed to downscale video frame: {e}")))?;
+
+    let rgb = resized.buffer();
+    let mut luma = Vec::with_capacity((size * size) as usize);
+    for pixel in rgb.chunks_exact(3) {
+        // Rec. 601 luma; the exact weights don't matter for a difference metric.
+        let y = 0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32;
+        luma.push(y.round() as u8);
+    }
+    Ok(luma)
+}
+
+fn mean_absolute_difference(a: &[u8], b: &[u8]) -> f32 {
+    if a.is_empty() || a.len() != b.len() {
+        return f32::INFINITY;
+    }
+    let sum: u32 = a
+        .iter()
+        .zip(b)
+        .map(|(&x, &y)| x.abs_diff(y) as u32)
+        .sum();
+    sum as f32 / a.len() as f32
+}
+
+/// Open the platform video decoder and adapt it to [`VideoFrameSource`]. The decoder pulls
+/// in FFmpeg, so it is gated behind the `video` feature to keep it out of builds that only
+/// index stills.
+#[cfg(feature = "video")]
+pub fn open_video_source(path: &str) -> MlResult<impl VideoFrameSource> {
+    ffmpeg::FfmpegFrameSource::open(path)
+}
+
+/// Fallback when the crate is built without the `video` feature: a clear error instead of a
+/// missing symbol, mirroring how the AVIF encoder surfaces an unavailable codec.
+#[cfg(not(feature = "video"))]
+pub fn open_video_source(_path: &str) -> MlResult<impl VideoFrameSource> {
+    Err::<UnavailableFrameSource, _>(MlError::Runtime(
+        "video decoding requires the `video` feature".to_string(),
+    ))
+}
+
+#[cfg(not(feature = "video"))]
+enum UnavailableFrameSource {}
+
+#[cfg(not(feature = "video"))]
+impl VideoFrameSource for UnavailableFrameSource {
+    fn next_frame(&mut self) -> MlResult<Option<VideoFrame>> {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "video")]
+mod ffmpeg {
+    use ffmpeg_next as ffmpeg;
+    use ffmpeg::format::{Pixel, input};
+    use ffmpeg::media::Type;
+    use ffmpeg::software::scaling::{context::Context as Scaler, flag::Flags};
+    use ffmpeg::util::frame::video::Video as VideoFrameBuffer;
+
+    use super::VideoFrame;
+    use crate::ml::{
+        error::{MlError, MlResult},
+        types::{DecodedImage, Dimensions},
+    };
+
+    fn map_err(e: ffmpeg::Error) -> MlError {
+        MlError::Decode(format!("ffmpeg: {e}"))
+    }
+
+    /// Pull RGB24 frames out of a container one at a time via FFmpeg.
+    pub struct FfmpegFrameSource {
+        input: ffmpeg::format::context::Input,
+        decoder: ffmpeg::decoder::Video,
+        scaler: Scaler,
+        stream_index: usize,
+        time_base: f64,
+    }
+
+    impl FfmpegFrameSource {
+        pub fn open(path: &str) -> MlResult<Self> {
+            ffmpeg::init().map_err(map_err)?;
+            let input = input(&path).map_err(map_err)?;
+            let stream = input
+                .streams()
+                .best(Type::Video)
+                .ok_or_else(|| MlError::Decode("no video stream in file".to_string()))?;
+            let stream_index = stream.index();
+            // Presentation timestamps are expressed in the stream's time base (a rational
+            // number of seconds); cache it as seconds-per-tick to convert to milliseconds.
+            let time_base = f64::from(stream.time_base());
+
+            let decoder_context =
+                ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+                    .map_err(map_err)?;
+            let decoder = decoder_context.decoder().video().map_err(map_err)?;
+            let scaler = Scaler::get(
+                decoder.format(),
+                decoder.width(),
+                decoder.height(),
+                Pixel::RGB24,
+                decoder.width(),
+                decoder.height(),
+                Flags::BILINEAR,
+            )
+            .map_err(map_err)?;
+
+            Ok(Self {
+                input,
+                decoder,
+                scaler,
+                stream_index,
+                time_base,
+            })
+        }
+
+        fn convert(&mut self, decoded: &VideoFrameBuffer) -> MlResult<VideoFrame> {
+            let mut rgb = VideoFrameBuffer::empty();
+            self.scaler.run(decoded, &mut rgb).map_err(map_err)?;
+
+            let width = rgb.width();
+            let height = rgb.height();
+            // FFmpeg rows are padded to `stride`; copy the valid `width * 3` bytes per row
+            // into a tightly packed buffer that matches `DecodedImage`'s layout.
+            let stride = rgb.stride(0);
+            let data = rgb.data(0);
+            let row_bytes = width as usize * 3;
+            let mut packed = Vec::with_capacity(row_bytes * height as usize);
+            for row in 0..height as usize {
+                let start = row * stride;
+                packed.extend_from_slice(&data[start..start + row_bytes]);
+            }
+
+            let timestamp_ms = decoded.timestamp().unwrap_or(0) as f64 * self.time_base * 1000.0;
+            Ok(VideoFrame {
+                timestamp_ms,
+                image: DecodedImage {
+                    dimensions: Dimensions { width, height },
+                    rgb: packed,
+                    source_bit_depth: 8,
+                },
+            })
+        }
+    }
+
+    impl super::VideoFrameSource for FfmpegFrameSource {
+        fn next_frame(&mut self) -> MlResult<Option<VideoFrame>> {
+            let mut decoded = VideoFrameBuffer::empty();
+            loop {
+                // Drain any frame already buffered in the decoder before reading more packets.
+                if self.decoder.receive_frame(&mut decoded).is_ok() {
+                    return self.convert(&decoded).map(Some);
+                }
+
+                match self.input.packets().next() {
+                    Some((stream, packet)) => {
+                        if stream.index() == self.stream_index {
+                            self.decoder.send_packet(&packet).map_err(map_err)?;
+                        }
+                    }
+                    None => {
+                        // Flush the decoder and hand back any trailing frame.
+                        self.decoder.send_eof().map_err(map_err)?;
+                        if self.decoder.receive_frame(&mut decoded).is_ok() {
+                            return self.convert(&decoded).map(Some);
+                        }
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ml::types::Dimensions;
+
+    struct VecFrameSource {
+        frames: std::vec::IntoIter<VideoFrame>,
+    }
+
+    impl VecFrameSource {
+        fn new(frames: Vec<VideoFrame>) -> Self {
+            Self {
+                frames: frames.into_iter(),
+            }
+        }
+    }
+
+    impl VideoFrameSource for VecFrameSource {
+        fn next_frame(&mut self) -> MlResult<Option<VideoFrame>> {
+            Ok(self.frames.next())
+        }
+    }
+
+    fn solid_frame(timestamp_ms: f64, value: u8, size: u32) -> VideoFrame {
+        VideoFrame {
+            timestamp_ms,
+            image: DecodedImage {
+                dimensions: Dimensions {
+                    width: size,
+                    height: size,
+                },
+                rgb: vec![value; (size * size * 3) as usize],
+                source_bit_depth: 8,
+            },
+        }
+    }
+
+    #[test]
+    fn identical_frames_yield_only_the_seed() {
+        let frames = (0..5)
+            .map(|i| solid_frame(i as f64 * 500.0, 100, 8))
+            .collect();
+        let selected =
+            select_keyframes(VecFrameSource::new(frames), &VideoKeyframeOptions::default())
+                .expect("selection should succeed");
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].timestamp_ms, 0.0);
+    }
+
+    #[test]
+    fn scene_changes_past_the_spacing_window_are_kept() {
+        let frames = vec![
+            solid_frame(0.0, 0, 8),
+            solid_frame(1200.0, 255, 8),
+            solid_frame(2400.0, 0, 8),
+        ];
+        let selected =
+            select_keyframes(VecFrameSource::new(frames), &VideoKeyframeOptions::default())
+                .expect("selection should succeed");
+        assert_eq!(selected.len(), 3);
+    }
+
+    #[test]
+    fn scene_changes_inside_the_spacing_window_are_dropped() {
+        let opts = VideoKeyframeOptions {
+            min_spacing_ms: 1000.0,
+            ..VideoKeyframeOptions::default()
+        };
+        let frames = vec![
+            solid_frame(0.0, 0, 8),
+            // A hard cut only 200 ms after the seed: a scene change, but too close in time.
+            solid_frame(200.0, 255, 8),
+            solid_frame(1300.0, 0, 8),
+        ];
+        let selected =
+            select_keyframes(VecFrameSource::new(frames), &opts).expect("selection should succeed");
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected[1].timestamp_ms, 1300.0);
+    }
+
+    #[test]
+    fn max_frames_caps_the_selection() {
+        let opts = VideoKeyframeOptions {
+            min_spacing_ms: 0.0,
+            max_frames: 2,
+            ..VideoKeyframeOptions::default()
+        };
+        let frames = (0..6)
+            .map(|i| solid_frame(i as f64 * 500.0, (i * 40) as u8, 8))
+            .collect();
+        let selected =
+            select_keyframes(VecFrameSource::new(frames), &opts).expect("selection should succeed");
+        assert_eq!(selected.len(), 2);
+    }
+}